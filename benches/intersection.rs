@@ -0,0 +1,63 @@
+//! Scalar against four-wide packet intersection, over the rays a camera shoots into the random scene
+//! Run with `cargo bench`
+
+#![allow(clippy::needless_return)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use raytracing::scene;
+use raytracing::simd::{F32x4, RayPacket4};
+use raytracing::{Aabb, Camera, CameraFrame, Float, Hitable, PerspectiveCamera, Point3, Ray, SamplerKind, Vector3};
+
+/// A 64x32 grid of camera rays, neighbors are next to each other like they would be in a packet
+fn camera_rays() -> Vec<Ray> {
+    let frame = CameraFrame::look_at(Point3::new(13.0, 2.0, 3.0), &Point3::origin(), &Vector3::new(0.0, 1.0, 0.0));
+    let camera = PerspectiveCamera::from_frame(frame, 20.0, 2.0, 0.0, 10.0);
+    let mut sampler = SamplerKind::Independent.create(1, 0);
+    let mut rays = Vec::new();
+    for y in 0..32 {
+        for x in 0..64 {
+            rays.push(camera.get_ray(x as Float / 64.0, y as Float / 32.0, &mut *sampler).unwrap());
+        }
+    }
+    return rays;
+}
+
+fn scene_intersection(criterion: &mut Criterion) {
+    let world = scene::random_scene(0).build();
+    let rays = camera_rays();
+
+    let mut group = criterion.benchmark_group("random scene, 2048 rays");
+    group.bench_function("scalar", |bencher| bencher.iter(|| {
+        rays.iter().filter(|ray| world.hit(black_box(ray), 0.001, Float::MAX).is_some()).count()
+    }));
+    group.bench_function("packet", |bencher| bencher.iter(|| {
+        rays.chunks_exact(4).map(|chunk| {
+            let packet = RayPacket4::new([&chunk[0], &chunk[1], &chunk[2], &chunk[3]]);
+            let mut hits = [None, None, None, None];
+            world.hit_packet(black_box(&packet), 0.001, &mut [Float::MAX; 4], &mut hits);
+            hits.iter().filter(|hit| hit.is_some()).count()
+        }).sum::<usize>()
+    }));
+    group.finish();
+}
+
+fn aabb_intersection(criterion: &mut Criterion) {
+    let aabb = Aabb::new(Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 2.0, 1.0));
+    let rays = camera_rays();
+
+    let mut group = criterion.benchmark_group("bounding box, 2048 rays");
+    group.bench_function("scalar", |bencher| bencher.iter(|| {
+        rays.iter().filter(|ray| black_box(&aabb).hit(ray, 0.001, Float::MAX)).count()
+    }));
+    group.bench_function("packet", |bencher| bencher.iter(|| {
+        rays.chunks_exact(4).map(|chunk| {
+            let packet = RayPacket4::new([&chunk[0], &chunk[1], &chunk[2], &chunk[3]]);
+            black_box(&aabb).hit_packet(&packet, F32x4::splat(0.001), F32x4::splat(f32::MAX)).bits().count_ones() as usize
+        }).sum::<usize>()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, scene_intersection, aabb_intersection);
+criterion_main!(benches);
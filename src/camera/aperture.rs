@@ -0,0 +1,172 @@
+use crate::luminance;
+use crate::sampler::hashing::hash;
+use crate::sampling::sample_concentric_disk;
+use crate::ImageTexture;
+
+use std::f32::consts::PI;
+use std::io;
+
+/// The shape of the lens opening, which is the shape out-of-focus highlights (bokeh) take
+#[derive(Clone)]
+pub enum ApertureShape {
+    Circle,
+    /// A regular polygon, like the opening formed by the blades of a real lens
+    /// rotation turns it counterclockwise, with 0 a corner points straight up
+    Polygon { blades: u32, rotation_deg: f32 },
+    /// Any shape, from a grayscale image: brighter pixels let through more light
+    Mask(ApertureMask),
+}
+
+impl ApertureShape {
+    /// Maps a 2D sample to a point on the aperture, within the unit circle for circles and polygons
+    /// and within [-1, 1] * [-1, 1] for masks
+    pub fn sample(&self, sample: (f32, f32)) -> (f32, f32) {
+        match self {
+            ApertureShape::Circle => {
+                let (x, y) = sample_concentric_disk(sample);
+                (x as f32, y as f32)
+            },
+            ApertureShape::Polygon { blades, rotation_deg } => sample_polygon(*blades.max(&3), rotation_deg.to_radians(), sample),
+            ApertureShape::Mask(mask) => mask.sample(sample),
+        }
+    }
+
+    /// A hash of the shape, see RenderSeeds
+    pub(crate) fn fingerprint(&self) -> u64 {
+        match self {
+            ApertureShape::Circle => hash(&[0]),
+            ApertureShape::Polygon { blades, rotation_deg } => hash(&[1, *blades as u64, rotation_deg.to_bits() as u64]),
+            ApertureShape::Mask(mask) => {
+                let mut values = vec![2, mask.width as u64, mask.height as u64];
+                values.extend(mask.row_cdf.iter().chain(mask.column_cdfs.iter()).map(|value| value.to_bits() as u64));
+                hash(&values)
+            },
+        }
+    }
+}
+
+/// Uniformly samples a regular polygon inscribed in the unit circle, by picking one of the triangles
+/// between the center and an edge and then a point in that triangle
+fn sample_polygon(blades: u32, rotation: f32, sample: (f32, f32)) -> (f32, f32) {
+    // the first coordinate picks the triangle, what's left of it is still uniform
+    let scaled = sample.0 * blades as f32;
+    let triangle = (scaled as u32).min(blades - 1);
+    let remaining = scaled - triangle as f32;
+
+    let corner = |index: u32| {
+        let angle = PI / 2.0 + rotation + 2.0 * PI * index as f32 / blades as f32;
+        (angle.cos(), angle.sin())
+    };
+    let (a, b) = (corner(triangle), corner(triangle + 1));
+
+    // uniform in the triangle (center, a, b)
+    let distance = remaining.sqrt();
+    let along = sample.1;
+    return (distance * ((1.0 - along) * a.0 + along * b.0), distance * ((1.0 - along) * a.1 + along * b.1));
+}
+
+/// An aperture shape from an image, sampled in proportion to brightness through a 2D CDF
+/// (pick a row by the total brightness of the rows, then a pixel in that row)
+#[derive(Clone)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// row_cdf[row] is the fraction of the brightness in the rows above row, height + 1 entries
+    row_cdf: Vec<f32>,
+    /// The same within each row, width + 1 entries per row
+    column_cdfs: Vec<f32>,
+}
+
+impl ApertureMask {
+    /// The image covers the whole lens square, so a white disk touching the edges is the usual circular aperture
+    pub fn new(image: &ImageTexture) -> io::Result<ApertureMask> {
+        let (width, height) = (image.width(), image.height());
+        let mut row_cdf = vec![0.0; height + 1];
+        let mut column_cdfs = vec![0.0; height * (width + 1)];
+        for y in 0..height {
+            let row = &mut column_cdfs[y * (width + 1)..(y + 1) * (width + 1)];
+            for x in 0..width {
+                row[x + 1] = row[x] + luminance(image.pixel(x, y)).max(0.0);
+            }
+
+            row_cdf[y + 1] = row_cdf[y] + row[width];
+            normalize(row);
+        }
+
+        if row_cdf[height] <= 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the aperture image is black, no light would get through"));
+        }
+
+        normalize(&mut row_cdf);
+        Ok(ApertureMask {
+            width: width,
+            height: height,
+            row_cdf: row_cdf,
+            column_cdfs: column_cdfs,
+        })
+    }
+
+    fn sample(&self, sample: (f32, f32)) -> (f32, f32) {
+        let (y, jitter_y) = sample_cdf(&self.row_cdf, sample.0);
+        let (x, jitter_x) = sample_cdf(&self.column_cdfs[y * (self.width + 1)..(y + 1) * (self.width + 1)], sample.1);
+        // row 0 is the top of the image
+        let lens_x = 2.0 * (x as f32 + jitter_x) / self.width as f32 - 1.0;
+        let lens_y = 1.0 - 2.0 * (y as f32 + jitter_y) / self.height as f32;
+        return (lens_x, lens_y);
+    }
+}
+
+/// Scales a running sum so it ends at 1, rows that are all black are left at 0
+fn normalize(cdf: &mut [f32]) {
+    let total = cdf[cdf.len() - 1];
+    if total > 0.0 {
+        for value in cdf.iter_mut() {
+            *value /= total;
+        }
+    }
+}
+
+/// Finds the bucket u falls in, and how far into the bucket it is (0..1)
+fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    // the last bucket whose start is at or below u, skipping empty buckets
+    let bucket = cdf.partition_point(|&start| start <= u).clamp(1, cdf.len() - 1) - 1;
+    let size = cdf[bucket + 1] - cdf[bucket];
+    let offset = if size > 0.0 { ((u - cdf[bucket]) / size).clamp(0.0, 0.999_999_9) } else { 0.5 };
+    return (bucket, offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::hashing::Pcg32;
+    use crate::Color;
+
+    #[test]
+    fn polygon_samples_stay_inside_the_polygon() {
+        let mut rng = Pcg32::new(3, 4);
+        for _ in 0..1000 {
+            let (x, y) = sample_polygon(5, 0.0, (rng.next_f32(), rng.next_f32()));
+            // the edges of a regular pentagon inscribed in the unit circle are cos(36 degrees) from the center
+            for edge in 0..5 {
+                let normal_angle = PI / 2.0 + (2.0 * edge as f32 + 1.0) * PI / 5.0;
+                assert!(x * normal_angle.cos() + y * normal_angle.sin() <= (PI / 5.0).cos() + 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_only_samples_bright_pixels() {
+        // 2x2, only the top right pixel lets light through
+        let black = Color::black();
+        let image = ImageTexture::new(2, 2, vec![black.clone(), Color::white(), black.clone(), black.clone()]);
+        let mask = ApertureMask::new(&image).unwrap();
+
+        let mut rng = Pcg32::new(5, 6);
+        for _ in 0..1000 {
+            let (x, y) = mask.sample((rng.next_f32(), rng.next_f32()));
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y), "({}, {}) is outside the top right quarter", x, y);
+        }
+
+        assert!(ApertureMask::new(&ImageTexture::new(1, 1, vec![black])).is_err());
+    }
+}
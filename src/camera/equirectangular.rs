@@ -0,0 +1,56 @@
+use crate::float::consts;
+use crate::Float;
+use crate::Camera;
+use crate::CameraFrame;
+use crate::Ray;
+use crate::Sampler;
+use crate::Vector3;
+
+use consts::PI;
+
+/// Sees everything around the camera: longitude runs left to right (-180 to 180 degrees, straight ahead in the middle)
+/// and latitude bottom to top (-90 to 90 degrees)
+/// This is the layout VR viewers and panorama sites expect, at a 2:1 aspect ratio
+pub struct EquirectangularCamera {
+    frame: CameraFrame,
+}
+
+impl EquirectangularCamera {
+    pub fn new(frame: CameraFrame) -> EquirectangularCamera {
+        EquirectangularCamera {
+            frame: frame,
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: Float, t: Float, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let direction = Vector3::new(latitude.cos() * longitude.sin(), latitude.sin(), -latitude.cos() * longitude.cos());
+        Some(Ray::new(self.frame.origin.clone(), self.frame.to_world(&direction)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+    use crate::SamplerKind;
+
+    #[test]
+    fn equirectangular_center_looks_ahead_and_edges_look_behind() {
+        let frame = CameraFrame::look_at(Point3::origin(), &Point3::new(1.0, 0.0, 0.0), &Vector3::new(0.0, 1.0, 0.0));
+        let camera = EquirectangularCamera::new(frame);
+        let mut sampler = SamplerKind::Independent.create(1, 0);
+
+        let ahead = camera.get_ray(0.5, 0.5, &mut *sampler).unwrap();
+        assert!((ahead.direction() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+
+        let behind = camera.get_ray(0.0, 0.5, &mut *sampler).unwrap();
+        assert!((behind.direction() - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-5);
+
+        let up = camera.get_ray(0.3, 1.0, &mut *sampler).unwrap();
+        assert!((up.direction() - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Float;
+use crate::Camera;
+use crate::CameraFrame;
+use crate::Ray;
+use crate::Sampler;
+use crate::Vector3;
+
+/// An equidistant fisheye: the angle away from the view direction grows linearly with the distance from the image center
+/// The image is a circle touching the top and bottom of the film, outside of it the film stays black
+pub struct FisheyeCamera {
+    frame: CameraFrame,
+    /// Half the field of view, in radians
+    half_fov: Float,
+    aspect_ratio: Float,
+}
+
+impl FisheyeCamera {
+    /// fov is how many degrees the image circle spans, 180 sees the whole hemisphere in front of the camera
+    pub fn new(frame: CameraFrame, fov_deg: Float, aspect_ratio: Float) -> FisheyeCamera {
+        FisheyeCamera {
+            frame: frame,
+            half_fov: fov_deg.to_radians() / 2.0,
+            aspect_ratio: aspect_ratio,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: Float, t: Float, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        // centered, with the image circle at radius 1
+        let x = (2.0 * s - 1.0) * self.aspect_ratio;
+        let y = 2.0 * t - 1.0;
+        let radius = (x * x + y * y).sqrt();
+        if radius > 1.0 {
+            return None;
+        }
+
+        let theta = radius * self.half_fov;
+        let phi = y.atan2(x);
+        let direction = Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+        Some(Ray::new(self.frame.origin.clone(), self.frame.to_world(&direction)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+    use crate::SamplerKind;
+
+    fn angle_between(a: &Vector3, b: &Vector3) -> Float {
+        Vector3::dot(&Vector3::unit_vector(a), &Vector3::unit_vector(b)).clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    #[test]
+    fn fisheye_center_looks_ahead_and_the_circle_edge_is_half_the_fov_away() {
+        let frame = CameraFrame::look_at(Point3::origin(), &Point3::new(0.0, 0.0, 5.0), &Vector3::new(0.0, 1.0, 0.0));
+        let camera = FisheyeCamera::new(frame, 120.0, 2.0);
+        let mut sampler = SamplerKind::Independent.create(1, 0);
+        let view_direction = Vector3::new(0.0, 0.0, 1.0);
+
+        let center = camera.get_ray(0.5, 0.5, &mut *sampler).unwrap();
+        assert!((Vector3::unit_vector(center.direction()) - &view_direction).magnitude() < 1e-5);
+
+        // the circle touches the top and bottom of the film, and is as wide as it is tall
+        for &(s, t) in [(0.5, 1.0), (0.5, 0.0), (0.25, 0.5), (0.75, 0.5)].iter() {
+            let edge = camera.get_ray(s, t, &mut *sampler).unwrap();
+            assert!((angle_between(edge.direction(), &view_direction) - 60.0).abs() < 1e-3);
+        }
+        let top = camera.get_ray(0.5, 1.0, &mut *sampler).unwrap();
+        assert!(top.direction().y() > 0.0);
+
+        assert!(camera.get_ray(0.0, 0.0, &mut *sampler).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::Float;
+use crate::Point3;
+use crate::Vector3;
+
+/// Where a camera sits and which way it faces, shared by every projection
+/// u points right, v up, and w backwards (the camera looks down -w)
+///
+/// Camera space is x right, y up, looking down -z (like Blender and OpenGL); every constructor
+/// below ends up at the same orthonormal u/v/w basis, they only differ in how the orientation is given
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraFrame {
+    pub origin: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl CameraFrame {
+    /// A camera at look_from looking at look_at, with v_up roughly "up"
+    pub fn look_at(look_from: Point3, look_at: &Point3, v_up: &Vector3) -> Self {
+        // Get the orthonormal vectors in camera space
+        let w = Vector3::unit_vector(&(&look_from - look_at));
+        let u = Vector3::unit_vector(&v_up.cross(&w));
+        let v = w.cross(&u);
+        CameraFrame {
+            origin: look_from,
+            u: u,
+            v: v,
+            w: w,
+        }
+    }
+
+    /// A camera turned yaw degrees left around the world's Y axis, then tilted pitch degrees up, then rolled
+    /// roll degrees counterclockwise around its view direction (the horizon in the image turns clockwise)
+    /// All zeros looks down -Z with Y up
+    pub fn from_yaw_pitch_roll(origin: Point3, yaw_deg: Float, pitch_deg: Float, roll_deg: Float) -> Self {
+        let rotation = Quaternion::from_axis_angle(&Vector3::new(0.0, 1.0, 0.0), yaw_deg.to_radians())
+            * Quaternion::from_axis_angle(&Vector3::new(1.0, 0.0, 0.0), pitch_deg.to_radians())
+            * Quaternion::from_axis_angle(&Vector3::new(0.0, 0.0, 1.0), roll_deg.to_radians());
+        CameraFrame::from_quaternion(origin, &rotation)
+    }
+
+    /// A camera rotated from camera space into the world by rotation
+    pub fn from_quaternion(origin: Point3, rotation: &Quaternion) -> Self {
+        let rotation = rotation.normalized();
+        CameraFrame {
+            origin: origin,
+            u: rotation.rotate(&Vector3::new(1.0, 0.0, 0.0)),
+            v: rotation.rotate(&Vector3::new(0.0, 1.0, 0.0)),
+            w: rotation.rotate(&Vector3::new(0.0, 0.0, 1.0)),
+        }
+    }
+
+    /// A camera from a row-major 4x4 camera-to-world matrix, like Blender's camera.matrix_world
+    /// The first three columns are the camera's x, y and z axes and the last column its position
+    /// Any scale in the matrix is ignored
+    pub fn from_matrix(matrix: &[[Float; 4]; 4]) -> Self {
+        let column = |index: usize| Vector3::new(matrix[0][index], matrix[1][index], matrix[2][index]);
+        CameraFrame {
+            origin: Point3::from_vector(column(3)),
+            u: Vector3::unit_vector(&column(0)),
+            v: Vector3::unit_vector(&column(1)),
+            w: Vector3::unit_vector(&column(2)),
+        }
+    }
+
+    /// A direction given in camera space (x right, y up, z backwards) turned into world space
+    pub fn to_world(&self, direction: &Vector3) -> Vector3 {
+        &self.u * direction.x() + &self.v * direction.y() + &self.w * direction.z()
+    }
+}
+
+/// A rotation, as w + xi + yj + zk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Quaternion { w: w, x: x, y: y, z: z }
+    }
+
+    /// Rotates angle radians counterclockwise around axis (looking down the axis towards the origin)
+    pub fn from_axis_angle(axis: &Vector3, angle: Float) -> Self {
+        let axis = Vector3::unit_vector(axis);
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quaternion::new(cos, axis.x() * sin, axis.y() * sin, axis.z() * sin)
+    }
+
+    pub fn normalized(&self) -> Self {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        Quaternion::new(self.w / length, self.x / length, self.y / length, self.z / length)
+    }
+
+    /// Rotates a vector by this (unit) quaternion
+    pub fn rotate(&self, vector: &Vector3) -> Vector3 {
+        // v + 2w(q x v) + 2q x (q x v), with q the vector part
+        let q = Vector3::new(self.x, self.y, self.z);
+        let t = 2.0 * q.cross(vector);
+        return vector + &t * self.w + q.cross(&t);
+    }
+}
+
+/// Rotating by lhs * rhs rotates by rhs first, then by lhs
+impl std::ops::Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_frames_agree(expected: &CameraFrame, actual: &CameraFrame) {
+        assert!(expected.origin.distance(&actual.origin) < 1e-5, "expected {:?}, got {:?}", expected.origin, actual.origin);
+        for (expected, actual) in [(&expected.u, &actual.u), (&expected.v, &actual.v), (&expected.w, &actual.w)] {
+            assert!((expected - actual).magnitude() < 1e-5, "expected {:?}, got {:?}", expected, actual);
+        }
+    }
+
+    /// The default camera of the random scene
+    fn look_at_frame() -> CameraFrame {
+        CameraFrame::look_at(Point3::new(13.0, 2.0, 3.0), &Point3::origin(), &Vector3::new(0.0, 1.0, 0.0))
+    }
+
+    #[test]
+    fn yaw_pitch_roll_agrees_with_look_at() {
+        // looking from (13, 2, 3) at the origin is (-13, -2, -3)
+        let yaw = Float::atan2(13.0, 3.0).to_degrees();
+        let pitch = (-2.0 / Float::sqrt(182.0)).asin().to_degrees();
+        assert_frames_agree(&look_at_frame(), &CameraFrame::from_yaw_pitch_roll(Point3::new(13.0, 2.0, 3.0), yaw, pitch, 0.0));
+
+        // rolling a quarter turn puts camera "up" where "left" was
+        let rolled = CameraFrame::look_at(Point3::origin(), &Point3::new(0.0, 0.0, -1.0), &Vector3::new(-1.0, 0.0, 0.0));
+        assert_frames_agree(&rolled, &CameraFrame::from_yaw_pitch_roll(Point3::origin(), 0.0, 0.0, 90.0));
+    }
+
+    #[test]
+    fn quaternion_agrees_with_look_at() {
+        // half a turn around Y looks down +Z instead of -Z
+        let rotation = Quaternion::new(0.0, 0.0, 1.0, 0.0);
+        let expected = CameraFrame::look_at(Point3::new(1.0, 2.0, 3.0), &Point3::new(1.0, 2.0, 4.0), &Vector3::new(0.0, 1.0, 0.0));
+        assert_frames_agree(&expected, &CameraFrame::from_quaternion(Point3::new(1.0, 2.0, 3.0), &rotation));
+    }
+
+    #[test]
+    fn matrix_agrees_with_look_at() {
+        let expected = look_at_frame();
+        let (u, v, w, origin) = (&expected.u, &expected.v, &expected.w, &expected.origin);
+        // a scaled matrix, like an object with scale 2 parented to the camera would give
+        let matrix = [
+            [2.0 * u.x(), 2.0 * v.x(), 2.0 * w.x(), origin.x()],
+            [2.0 * u.y(), 2.0 * v.y(), 2.0 * w.y(), origin.y()],
+            [2.0 * u.z(), 2.0 * v.z(), 2.0 * w.z(), origin.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        assert_frames_agree(&expected, &CameraFrame::from_matrix(&matrix));
+    }
+}
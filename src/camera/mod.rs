@@ -0,0 +1,67 @@
+use crate::sampler::hashing::hash;
+use crate::Float;
+use crate::Ray;
+use crate::Sampler;
+
+pub use self::aperture::*;
+pub use self::equirectangular::*;
+pub use self::fisheye::*;
+pub use self::frame::*;
+pub use self::orthographic::*;
+pub use self::perspective::*;
+pub use self::physical::*;
+
+mod aperture;
+mod equirectangular;
+mod fisheye;
+mod frame;
+mod orthographic;
+mod perspective;
+mod physical;
+
+/// Turns a point on the film into a ray into the scene
+pub trait Camera {
+    /// s runs left to right and t bottom to top over the film, both 0..1
+    /// The sampler picks anything else the camera needs, like where on the lens the ray starts
+    /// Returns None where the film sees nothing, like the corners outside a fisheye's image circle
+    fn get_ray(&self, s: Float, t: Float, sampler: &mut dyn Sampler) -> Option<Ray>;
+}
+
+/// Which projection the camera uses
+#[derive(Clone, Default)]
+pub enum Projection {
+    /// A thin lens with depth of field, like a real camera
+    #[default]
+    Perspective,
+    /// A thin lens described by focal length, sensor size and f-number instead of a field of view and aperture
+    Physical(PhysicalCamera),
+    /// Parallel rays, view_height world units tall. No perspective, for elevations and plans
+    Orthographic { view_height: Float },
+    /// An equidistant fisheye with a circular image fov degrees across
+    Fisheye { fov: Float },
+    /// The full sphere around the camera, for VR panoramas. Wants a 2:1 image
+    Equirectangular,
+}
+
+impl Projection {
+    /// A hash of the projection and all its parameters, see RenderSeeds
+    pub(crate) fn fingerprint(&self) -> u64 {
+        let bits = |value: Float| (value as f64).to_bits();
+        match self {
+            Projection::Perspective => hash(&[0]),
+            Projection::Physical(camera) => hash(&[
+                1,
+                bits(camera.focal_length),
+                bits(camera.sensor_width),
+                bits(camera.f_number),
+                bits(camera.shutter_speed),
+                bits(camera.iso),
+                bits(camera.focus_distance),
+                camera.aperture_shape.fingerprint(),
+            ]),
+            Projection::Orthographic { view_height } => hash(&[2, bits(*view_height)]),
+            Projection::Fisheye { fov } => hash(&[3, bits(*fov)]),
+            Projection::Equirectangular => hash(&[4]),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::Float;
+use crate::Camera;
+use crate::CameraFrame;
+use crate::Ray;
+use crate::Sampler;
+
+/// Every ray points straight ahead, so sizes don't shrink with distance
+/// Good for architectural elevations and plans, where lengths should be measurable off the image
+pub struct OrthographicCamera {
+    frame: CameraFrame,
+    view_width: Float,
+    view_height: Float,
+}
+
+impl OrthographicCamera {
+    /// view_height is how much of the world (in world units) the image shows bottom to top
+    pub fn new(frame: CameraFrame, view_height: Float, aspect_ratio: Float) -> OrthographicCamera {
+        OrthographicCamera {
+            frame: frame,
+            view_width: view_height * aspect_ratio,
+            view_height: view_height,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: Float, t: Float, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let origin = &self.frame.origin + &self.frame.u * ((s - 0.5) * self.view_width) + &self.frame.v * ((t - 0.5) * self.view_height);
+        Some(Ray::new(origin, -&self.frame.w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+    use crate::SamplerKind;
+    use crate::Vector3;
+
+    #[test]
+    fn orthographic_rays_are_parallel_to_the_view_direction() {
+        let frame = CameraFrame::look_at(Point3::new(0.0, 3.0, 0.0), &Point3::new(4.0, 0.0, 0.0), &Vector3::new(0.0, 1.0, 0.0));
+        let camera = OrthographicCamera::new(frame, 8.0, 2.0);
+        let mut sampler = SamplerKind::Independent.create(1, 0);
+        let view_direction = Vector3::new(0.8, -0.6, 0.0);
+
+        for &(s, t) in [(0.5, 0.5), (0.0, 0.0), (1.0, 0.25), (0.1, 1.0)].iter() {
+            let ray = camera.get_ray(s, t, &mut *sampler).unwrap();
+            assert!((Vector3::unit_vector(ray.direction()) - &view_direction).magnitude() < 1e-5);
+        }
+
+        // the image is 16 by 8 world units, so opposite corners start that far apart
+        let bottom_left = camera.get_ray(0.0, 0.0, &mut *sampler).unwrap();
+        let top_right = camera.get_ray(1.0, 1.0, &mut *sampler).unwrap();
+        let diagonal = top_right.origin() - bottom_left.origin();
+        assert!((diagonal.magnitude() - (16.0 as Float).hypot(8.0)).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::float::consts;
+use crate::Float;
+use crate::ApertureShape;
+use crate::Camera;
+use crate::CameraFrame;
+use crate::Point3;
+use crate::Ray;
+use crate::Sampler;
+use crate::Vector3;
+
+/// A thin-lens perspective camera
+pub struct PerspectiveCamera {
+    /// The aperture of the lens for the Camera, used in determining the depth-of-field / defocus distance
+    aperture: Float,
+
+    /// The shape of the aperture, and so of the bokeh
+    aperture_shape: ApertureShape,
+
+    /// The lower-left-corner of the Camera, used to determine all other points relative to it
+    lower_left_corner: Point3,
+
+    /// How wide the camera is
+    horizontal: Vector3,
+
+    /// How tall the camera is
+    vertical: Vector3,
+
+    /// Where the camera is located in 3d space, and which way it faces
+    frame: CameraFrame,
+}
+
+impl PerspectiveCamera {
+    /// Looking from look_from at look_at, with v_up roughly "up"
+    /// For a roll angle, a quaternion or a matrix instead of v_up, build a CameraFrame and use from_frame
+    pub fn new(look_from: Point3, look_at: Point3, v_up: Vector3, vfov_deg: Float, aspect_ratio: Float, aperture: Float, focus_dist: Float) -> PerspectiveCamera {
+        PerspectiveCamera::from_frame(CameraFrame::look_at(look_from, &look_at, &v_up), vfov_deg, aspect_ratio, aperture, focus_dist)
+    }
+
+    pub fn from_frame(frame: CameraFrame, vfov_deg: Float, aspect_ratio: Float, aperture: Float, focus_dist: Float) -> PerspectiveCamera {
+        // book prefers passing in degrees and determining radians
+        let theta_radians = vfov_deg * consts::PI / 180.0;
+
+        // We're trying to find the "Center" of where our camera is pointing at
+        // We know the field_of_view so its just finding the "Opposite" line
+        // Only the center is half of that line
+        let half_height = (theta_radians / 2.0).tan();
+
+        // The aspect ratio will be something like "16:9" -- so its simple to derive width from height
+        let half_width = aspect_ratio * half_height;
+
+        let horizontal = half_width * focus_dist * &frame.u;
+        let vertical = half_height * focus_dist * &frame.v;
+
+        PerspectiveCamera {
+            aperture: aperture,
+            aperture_shape: ApertureShape::Circle,
+            lower_left_corner: &frame.origin - &horizontal - &vertical - focus_dist * &frame.w,
+            horizontal: 2.0 * horizontal,
+            vertical: 2.0 * vertical,
+            frame: frame,
+        }
+    }
+
+    /// Swaps the circular aperture for another shape, aperture is then the diameter of its bounding circle (or square for masks)
+    pub fn with_aperture_shape(mut self, aperture_shape: ApertureShape) -> PerspectiveCamera {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    /// Returns a ray broadcasting "Into" the negative Z axis (away from Camera)
+    /// The sampler picks where on the lens the ray starts
+    fn get_ray(&self, s: Float, t: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // This is a thin lens: rays start anywhere on the aperture and converge on the focus plane
+        // Only the focus plane is sharp, everything else blurs -- that's the depth of field
+        let (lens_x, lens_y) = self.aperture_shape.sample(sampler.get_2d());
+        let lens_radius = self.aperture / 2.0;
+        let offset = &self.frame.u * (lens_radius * lens_x as Float) + &self.frame.v * (lens_radius * lens_y as Float);
+
+        let ray_origin = &self.frame.origin + offset;
+        let direction = &self.lower_left_corner + &(&(&self.horizontal * s) + &(&self.vertical * t)) - &ray_origin;
+        Some(Ray::new(ray_origin, direction))
+    }
+}
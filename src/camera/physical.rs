@@ -0,0 +1,84 @@
+use crate::Float;
+use crate::ApertureShape;
+use crate::CameraFrame;
+use crate::PerspectiveCamera;
+
+/// A camera described the way a photographer would: lens, sensor, and exposure settings
+/// World units are taken to be meters
+///
+/// The focal length and sensor size give the field of view, the focal length and f-number the size of
+/// the aperture (and with it the depth of field), and the f-number, shutter speed and ISO the exposure
+/// Build the actual camera with perspective_camera(), and add exposure_stops() to the PostProcess exposure
+#[derive(Clone)]
+pub struct PhysicalCamera {
+    /// In millimeters, 50 is a "normal" lens on a full frame camera
+    pub focal_length: Float,
+    /// In millimeters, 36 for full frame. The sensor height follows from the image's aspect ratio
+    pub sensor_width: Float,
+    /// Focal length divided by the aperture diameter, smaller is a wider aperture with shallower depth of field
+    pub f_number: Float,
+    /// How long the shutter is open, in seconds
+    pub shutter_speed: Float,
+    /// Sensor sensitivity, 100 is the usual base ISO
+    pub iso: Float,
+    /// Distance to the sharpest plane, in meters
+    pub focus_distance: Float,
+    pub aperture_shape: ApertureShape,
+}
+
+/// The exposure that renders the scene's radiance as-is (0 stops): sky radiance around 1 is treated
+/// like an overcast day, which f/8, 1/125 s at ISO 100 exposes correctly
+const REFERENCE_EXPOSURE: Float = (1.0 / 125.0) * 100.0 / (8.0 * 8.0);
+
+impl PhysicalCamera {
+    /// The vertical field of view in degrees
+    pub fn vfov(&self, aspect_ratio: Float) -> Float {
+        let sensor_height = self.sensor_width / aspect_ratio;
+        return 2.0 * (sensor_height / (2.0 * self.focal_length)).atan().to_degrees();
+    }
+
+    /// The diameter of the aperture, in meters
+    pub fn aperture_diameter(&self) -> Float {
+        self.focal_length / self.f_number / 1000.0
+    }
+
+    /// How much brighter (positive) or darker (negative) the image is than the reference exposure, in stops
+    /// Every halving of the shutter speed or ISO, or every full f-stop up, is one stop darker
+    /// (f32 like the PostProcess exposure it's added to)
+    pub fn exposure_stops(&self) -> f32 {
+        let exposure = self.shutter_speed * self.iso / (self.f_number * self.f_number);
+        return (exposure / REFERENCE_EXPOSURE).log2() as f32;
+    }
+
+    pub fn perspective_camera(&self, frame: CameraFrame, aspect_ratio: Float) -> PerspectiveCamera {
+        PerspectiveCamera::from_frame(frame, self.vfov(aspect_ratio), aspect_ratio, self.aperture_diameter(), self.focus_distance)
+            .with_aperture_shape(self.aperture_shape.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_camera_matches_photographic_rules_of_thumb() {
+        let camera = PhysicalCamera {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            f_number: 8.0,
+            shutter_speed: 1.0 / 125.0,
+            iso: 100.0,
+            focus_distance: 10.0,
+            aperture_shape: ApertureShape::Circle,
+        };
+
+        // a 50mm lens on full frame (36x24) sees about 27 degrees vertically
+        assert!((camera.vfov(1.5) - 26.99).abs() < 0.01);
+        assert!((camera.aperture_diameter() - 0.00625).abs() < 1e-6);
+        assert!(camera.exposure_stops().abs() < 1e-5);
+
+        // opening up one stop and doubling the ISO is two stops brighter
+        let brighter = PhysicalCamera { f_number: 8.0 / Float::sqrt(2.0), iso: 200.0, ..camera.clone() };
+        assert!((brighter.exposure_stops() - 2.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::Film;
+use crate::Color;
+use crate::Float;
+use crate::Normal3;
+use crate::Point3;
+use crate::Vector3;
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The auxiliary buffers ("arbitrary output variables") kept next to the beauty image, for compositing and denoising
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    /// The albedo of the first surface hit, or the sky's color where the camera sees the sky
+    Albedo,
+    /// The shading normal at the first hit, in world space
+    Normal,
+    /// Distance along the camera ray to the first hit
+    Depth,
+    /// World space position of the first hit
+    Position,
+    ObjectId,
+    MaterialId,
+    /// Light that reached the camera after at most one bounce (including the sky seen directly)
+    Direct,
+    /// Light that bounced more than once, direct + indirect is the beauty image
+    Indirect,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Position, Aov::ObjectId, Aov::MaterialId, Aov::Direct, Aov::Indirect];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+        }
+    }
+
+    /// Single channel AOVs are written as grayscale images
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, Aov::Depth | Aov::ObjectId | Aov::MaterialId)
+    }
+}
+
+/// The AOVs of a single camera sample
+pub struct AovSample {
+    pub albedo: Color,
+    pub direct: Color,
+    pub indirect: Color,
+    /// The first surface the camera ray hit, None if it flew off into the sky
+    pub surface: Option<AovSurface>,
+}
+
+/// Black, having hit nothing
+impl Default for AovSample {
+    fn default() -> Self {
+        AovSample {
+            albedo: Color::black(),
+            direct: Color::black(),
+            indirect: Color::black(),
+            surface: None,
+        }
+    }
+}
+
+pub struct AovSurface {
+    pub normal: Normal3,
+    pub depth: f32,
+    pub position: Point3,
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+/// The AOVs accumulated in one pixel
+///
+/// AOVs aren't splatted through the reconstruction filter, every sample only counts in its own pixel:
+/// blending object IDs makes no sense, and the denoiser wants sharp features
+/// The surface AOVs are averaged over the samples that hit something, so silhouettes don't fade towards "nothing"
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AovPixel {
+    pub(crate) samples: u32,
+    pub(crate) albedo: Color,
+    pub(crate) direct: Color,
+    pub(crate) indirect: Color,
+    pub(crate) hits: u32,
+    /// Summed normals and positions are neither normals nor points, so the sums are kept as plain vectors
+    pub(crate) normal: Vector3,
+    pub(crate) depth: f32,
+    pub(crate) position: Vector3,
+    /// The IDs of the first sample that hit something
+    pub(crate) object_id: u32,
+    pub(crate) material_id: u32,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        AovPixel {
+            samples: 0,
+            albedo: Color::black(),
+            direct: Color::black(),
+            indirect: Color::black(),
+            hits: 0,
+            normal: Vector3::new(0.0, 0.0, 0.0),
+            depth: 0.0,
+            position: Vector3::new(0.0, 0.0, 0.0),
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+}
+
+impl AovPixel {
+    pub(crate) fn add(&mut self, sample: &AovSample) {
+        self.samples += 1;
+        self.albedo += &sample.albedo;
+        self.direct += &sample.direct;
+        self.indirect += &sample.indirect;
+        if let Some(surface) = &sample.surface {
+            if self.hits == 0 {
+                self.object_id = surface.object_id;
+                self.material_id = surface.material_id;
+            }
+
+            self.hits += 1;
+            self.normal += surface.normal.as_vector();
+            self.depth += surface.depth;
+            self.position += surface.position.as_vector();
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &AovPixel) {
+        if self.hits == 0 {
+            self.object_id = other.object_id;
+            self.material_id = other.material_id;
+        }
+
+        self.samples += other.samples;
+        self.albedo += &other.albedo;
+        self.direct += &other.direct;
+        self.indirect += &other.indirect;
+        self.hits += other.hits;
+        self.normal += &other.normal;
+        self.depth += other.depth;
+        self.position += &other.position;
+    }
+
+    /// The averaged albedo, which the denoiser divides out of the radiance
+    pub(crate) fn albedo(&self) -> Color {
+        &self.albedo / self.samples.max(1) as f32
+    }
+
+    /// The averaged value, scalars are repeated in all three channels
+    /// Pixels without samples are black, and pixels that never hit anything have no normal, depth or position
+    fn value(&self, aov: Aov) -> Vector3 {
+        let samples = self.samples.max(1) as f32;
+        let hits = self.hits.max(1) as Float;
+        match aov {
+            Aov::Albedo => channels(&self.albedo()),
+            Aov::Normal => {
+                let normal = &self.normal / hits;
+                if normal.magnitude() > 0.0 { Vector3::unit_vector(&normal) } else { normal }
+            },
+            Aov::Depth => Vector3::new(1.0, 1.0, 1.0) * (self.depth as Float / hits),
+            Aov::Position => &self.position / hits,
+            Aov::ObjectId => Vector3::new(1.0, 1.0, 1.0) * self.object_id as Float,
+            Aov::MaterialId => Vector3::new(1.0, 1.0, 1.0) * self.material_id as Float,
+            Aov::Direct => channels(&(&self.direct / samples)),
+            Aov::Indirect => channels(&(&self.indirect / samples)),
+        }
+    }
+}
+
+/// AOV images are plain data, colors included
+fn channels(color: &Color) -> Vector3 {
+    Vector3::new(color.r() as Float, color.g() as Float, color.b() as Float)
+}
+
+impl Film {
+    /// Accumulates the AOVs of a sample taken inside pixel (x, y)
+    pub fn add_aov_sample(&mut self, x: u32, y: u32, sample: &AovSample) {
+        let index = self.index(x, y);
+        self.aovs[index].add(sample);
+    }
+
+    /// The value of an AOV at pixel (x, y), where y = 0 is the bottom row
+    /// Scalar AOVs (depth and the IDs) are repeated in all three channels
+    pub fn aov(&self, aov: Aov, x: u32, y: u32) -> Vector3 {
+        self.aovs[self.index(x, y)].value(aov)
+    }
+
+    /// Writes an AOV as a PFM (portable float map), which keeps the values exactly as they are
+    /// PFM stores the bottom row first, like the film
+    pub fn write_aov_pfm<W: Write>(&self, aov: Aov, writer: &mut W) -> io::Result<()> {
+        let header = if aov.is_scalar() { "Pf" } else { "PF" };
+        // a negative scale means little-endian
+        write!(writer, "{}\n{} {}\n-1.0\n", header, self.width, self.height)?;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.aov(aov, x, y);
+                // PFM is always 32-bit
+                if aov.is_scalar() {
+                    writer.write_all(&(value.x() as f32).to_le_bytes())?;
+                } else {
+                    for channel in [value.x(), value.y(), value.z()] {
+                        writer.write_all(&(channel as f32).to_le_bytes())?;
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Writes every AOV into directory as <name>.pfm, atomically like save_ppm
+    pub fn save_aovs<P: AsRef<Path>>(&self, directory: P) -> io::Result<()> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        for aov in Aov::ALL.iter() {
+            let path = directory.join(format!("{}.pfm", aov.name()));
+            let temporary_path = directory.join(format!("{}.pfm.tmp", aov.name()));
+
+            let mut writer = BufWriter::new(File::create(&temporary_path)?);
+            self.write_aov_pfm(*aov, &mut writer)?;
+            writer.into_inner()?.sync_all()?;
+            fs::rename(&temporary_path, path)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Filter;
+
+    #[test]
+    fn surface_aovs_average_over_hits_only() {
+        let mut film = Film::new(1, 1, Filter::default());
+        let sky = AovSample {
+            albedo: Color::white(),
+            direct: Color::white(),
+            indirect: Color::black(),
+            surface: None,
+        };
+        let hit = AovSample {
+            albedo: Color::gray(0.5),
+            direct: Color::black(),
+            indirect: Color::gray(0.5),
+            surface: Some(AovSurface {
+                normal: Normal3::new(0.0, 2.0, 0.0),
+                depth: 4.0,
+                position: Point3::new(1.0, 2.0, 3.0),
+                object_id: 7,
+                material_id: 3,
+            }),
+        };
+        film.add_aov_sample(0, 0, &sky);
+        film.add_aov_sample(0, 0, &hit);
+
+        assert_eq!(Vector3::new(0.75, 0.75, 0.75), film.aov(Aov::Albedo, 0, 0));
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), film.aov(Aov::Normal, 0, 0));
+        assert_eq!(4.0, film.aov(Aov::Depth, 0, 0).x());
+        assert_eq!(7.0, film.aov(Aov::ObjectId, 0, 0).x());
+        assert_eq!(Vector3::new(0.25, 0.25, 0.25), film.aov(Aov::Indirect, 0, 0));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::Aov;
+use crate::Color;
+use crate::Film;
+
+/// Edge-avoiding à-trous wavelet denoiser (Dammertz et al. 2010), for usable images at low sample counts
+///
+/// Every iteration blurs with a 5x5 B3-spline kernel whose taps are spread twice as far apart as the
+/// iteration before, so a few cheap iterations cover a wide area
+/// Each tap is weighted down when it differs from the center pixel in color, normal or depth, so the blur
+/// stops at edges and object boundaries instead of smearing across them
+///
+/// The radiance is divided by the albedo before filtering and multiplied back afterwards, so only the
+/// lighting is blurred and textures stay sharp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: u32,
+    /// How different two colors may be before they stop blurring together, halved every iteration
+    pub sigma_color: f32,
+    /// The same for normals (as the distance between unit normals)
+    pub sigma_normal: f32,
+    /// The same for depth, relative to the center pixel's depth
+    pub sigma_depth: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            sigma_color: 0.5,
+            sigma_normal: 0.3,
+            sigma_depth: 0.05,
+        }
+    }
+}
+
+/// The B3-spline, the 1D weights of the 5x5 kernel
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+impl Denoiser {
+    /// Returns a copy of the film with denoised radiance, ready to be written like any other film
+    /// The sample statistics and AOVs are copied over untouched
+    pub fn denoise(&self, film: &Film) -> Film {
+        let width = film.width as i64;
+        let height = film.height as i64;
+        let num_pixels = film.weights.len();
+
+        let mut albedo = Vec::with_capacity(num_pixels);
+        let mut normals = Vec::with_capacity(num_pixels);
+        let mut depths = Vec::with_capacity(num_pixels);
+        let mut illumination = Vec::with_capacity(num_pixels);
+        // same order as the film's buffers: top row first
+        for y in (0..film.height).rev() {
+            for x in 0..film.width {
+                let pixel_albedo = film.aovs[film.index(x, y)].albedo();
+                illumination.push(demodulate(&film.pixel(x, y), &pixel_albedo));
+                albedo.push(pixel_albedo);
+                normals.push(film.aov(Aov::Normal, x, y));
+                depths.push(film.aov(Aov::Depth, x, y).x() as f32);
+            }
+        }
+
+        let mut filtered = illumination.clone();
+        for iteration in 0..self.iterations {
+            let step = 1i64 << iteration;
+            let sigma_color = self.sigma_color / (1 << iteration) as f32;
+            for row in 0..height {
+                for column in 0..width {
+                    let center = (row * width + column) as usize;
+                    let mut sum = Color::black();
+                    let mut total_weight = 0.0;
+                    for (j, kernel_y) in KERNEL.iter().enumerate() {
+                        let tap_row = row + (j as i64 - 2) * step;
+                        if tap_row < 0 || tap_row >= height {
+                            continue;
+                        }
+
+                        for (i, kernel_x) in KERNEL.iter().enumerate() {
+                            let tap_column = column + (i as i64 - 2) * step;
+                            if tap_column < 0 || tap_column >= width {
+                                continue;
+                            }
+
+                            let tap = (tap_row * width + tap_column) as usize;
+                            let color_distance = distance_squared(&illumination[tap], &illumination[center]);
+                            let normal_distance = (&normals[tap] - &normals[center]).magnitude_squared() as f32;
+                            let depth_distance = (depths[tap] - depths[center]) / (self.sigma_depth * depths[center].max(0.001));
+                            let weight = kernel_x * kernel_y
+                                * (-color_distance / (sigma_color * sigma_color)).exp()
+                                * (-normal_distance / (self.sigma_normal * self.sigma_normal)).exp()
+                                * (-depth_distance * depth_distance).exp();
+
+                            sum += &illumination[tap] * weight;
+                            total_weight += weight;
+                        }
+                    }
+
+                    // the center tap always has a weight, but it can underflow to 0 for extreme sigmas
+                    filtered[center] = if total_weight > 0.0 { sum / total_weight } else { illumination[center].clone() };
+                }
+            }
+
+            std::mem::swap(&mut illumination, &mut filtered);
+        }
+
+        let mut denoised = Film {
+            width: film.width,
+            height: film.height,
+            filter: film.filter,
+            weighted_radiance: Vec::with_capacity(num_pixels),
+            weights: vec![1.0; num_pixels],
+            statistics: film.statistics.clone(),
+            aovs: film.aovs.clone(),
+        };
+        for index in 0..num_pixels {
+            denoised.weighted_radiance.push(remodulate(&illumination[index], &albedo[index]));
+        }
+
+        return denoised;
+    }
+}
+
+/// Black albedo would divide by zero, and nearly black albedo would blow the noise up
+const MIN_ALBEDO: f32 = 0.01;
+
+fn demodulate(color: &Color, albedo: &Color) -> Color {
+    Color::new(color.r() / albedo.r().max(MIN_ALBEDO), color.g() / albedo.g().max(MIN_ALBEDO), color.b() / albedo.b().max(MIN_ALBEDO))
+}
+
+fn remodulate(illumination: &Color, albedo: &Color) -> Color {
+    illumination * albedo.map(|channel| channel.max(MIN_ALBEDO))
+}
+
+fn distance_squared(color: &Color, other: &Color) -> f32 {
+    let difference = color - other;
+    difference.r() * difference.r() + difference.g() * difference.g() + difference.b() * difference.b()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::hashing::Pcg32;
+    use crate::{AovSample, AovSurface, Filter, Float, Normal3, Point3};
+
+    fn add_sample(film: &mut Film, x: u32, y: u32, color: f32, normal: Normal3) {
+        film.add_sample(x, y, (0.5, 0.5), &Color::gray(color));
+        film.add_aov_sample(x, y, &AovSample {
+            albedo: Color::white(),
+            direct: Color::gray(color),
+            indirect: Color::black(),
+            surface: Some(AovSurface {
+                normal: normal,
+                depth: 1.0,
+                position: Point3::new(x as Float, y as Float, 0.0),
+                object_id: 1,
+                material_id: 1,
+            }),
+        });
+    }
+
+    #[test]
+    fn denoiser_smooths_noise_but_keeps_edges() {
+        let mut film = Film::new(16, 16, Filter::default());
+        let mut rng = Pcg32::new(1, 2);
+        for y in 0..16 {
+            for x in 0..16 {
+                // left half faces up and right half faces sideways, with some noise on top
+                let noise = 0.2 * rng.next_f32() - 0.1;
+                let (color, normal) = if x < 8 { (0.2, Normal3::new(0.0, 1.0, 0.0)) } else { (0.8, Normal3::new(1.0, 0.0, 0.0)) };
+                add_sample(&mut film, x, y, color + noise, normal);
+            }
+        }
+
+        let denoised = Denoiser::default().denoise(&film);
+
+        // well inside each half the checkerboard averages out
+        assert!((denoised.pixel(3, 8).r() - 0.2).abs() < 0.02);
+        assert!((denoised.pixel(12, 8).r() - 0.8).abs() < 0.02);
+        // and the halves don't bleed into each other at the edge
+        assert!((denoised.pixel(7, 8).r() - 0.2).abs() < 0.05);
+        assert!((denoised.pixel(8, 8).r() - 0.8).abs() < 0.05);
+    }
+}
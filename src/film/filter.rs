@@ -0,0 +1,120 @@
+use crate::sampler::hashing::hash;
+
+/// Reconstruction filters decide how much a sample contributes to the pixels around it
+/// Every filter is separable: weight(dx, dy) = f(dx) * f(dy), where dx and dy are measured in pixels
+///
+/// A box with radius 0.5 is the classic "average the samples inside the pixel"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f32 },
+    /// Linear falloff, reaching 0 at the radius
+    Tent { radius: f32 },
+    /// sigma is the standard deviation in pixels, the curve is shifted down so it reaches 0 at the radius
+    Gaussian { radius: f32, sigma: f32 },
+    /// The cubic from Mitchell and Netravali's "Reconstruction Filters in Computer Graphics"
+    /// b = c = 1/3 is their recommendation, the negative lobes sharpen the image a bit
+    Mitchell { radius: f32, b: f32, c: f32 },
+    /// sinc windowed by a wider sinc, tau is the number of lobes
+    Lanczos { radius: f32, tau: f32 },
+}
+
+impl Filter {
+    /// A hash of the filter and its parameters, see RenderSeeds
+    pub(crate) fn fingerprint(&self) -> u64 {
+        let values = match *self {
+            Filter::Box { radius } => [0, radius.to_bits(), 0, 0],
+            Filter::Tent { radius } => [1, radius.to_bits(), 0, 0],
+            Filter::Gaussian { radius, sigma } => [2, radius.to_bits(), sigma.to_bits(), 0],
+            Filter::Mitchell { radius, b, c } => [3, radius.to_bits(), b.to_bits(), c.to_bits()],
+            Filter::Lanczos { radius, tau } => [4, radius.to_bits(), tau.to_bits(), 0],
+        };
+        return hash(&values.map(u64::from));
+    }
+
+    /// How far (in pixels) a sample reaches in each direction
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius } => radius,
+            Filter::Tent { radius } => radius,
+            Filter::Gaussian { radius, .. } => radius,
+            Filter::Mitchell { radius, .. } => radius,
+            Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// The weight of a sample at offset (dx, dy) from the center of a pixel
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        return self.evaluate_1d(dx) * self.evaluate_1d(dy);
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => radius - x,
+            Filter::Gaussian { radius, sigma } => {
+                let gaussian = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            },
+            Filter::Mitchell { radius, b, c } => {
+                // the cubic is defined over [0, 2]
+                let x = 2.0 * x / radius;
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+                }
+            },
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+/// Normalized sinc, sin(PI * x) / (PI * x)
+fn sinc(x: f32) -> f32 {
+    let x = std::f32::consts::PI * x;
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+
+    return x.sin() / x;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_peaks_at_center_and_vanishes_at_radius() {
+        let filters = [
+            Filter::Box { radius: 0.5 },
+            Filter::Tent { radius: 1.0 },
+            Filter::Gaussian { radius: 1.5, sigma: 0.5 },
+            Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 },
+            Filter::Lanczos { radius: 2.0, tau: 2.0 },
+        ];
+
+        for filter in filters.iter() {
+            let peak = filter.evaluate(0.0, 0.0);
+            assert!(peak > 0.0, "{:?}", filter);
+            assert!(filter.evaluate(0.25, 0.0) <= peak, "{:?}", filter);
+            assert!(filter.evaluate(filter.radius() + 0.01, 0.0) == 0.0, "{:?}", filter);
+            if let Filter::Box { .. } = filter {
+                continue;
+            }
+
+            // everything but the box fades out smoothly
+            assert!(filter.evaluate(filter.radius() - 0.001, 0.0).abs() < 0.01, "{:?}", filter);
+        }
+    }
+}
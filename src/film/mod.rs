@@ -0,0 +1,185 @@
+use crate::Color;
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub use self::aov::*;
+pub use self::denoise::*;
+pub use self::filter::*;
+pub use self::postprocess::*;
+pub use self::state::*;
+pub use self::statistics::*;
+pub use self::tonemap::*;
+
+mod aov;
+mod denoise;
+mod filter;
+mod postprocess;
+mod state;
+mod statistics;
+mod tonemap;
+
+/// The Film collects the radiance of every sample, in linear light
+/// Nothing is clamped or gamma corrected here -- that happens in the PostProcess stage when writing an image
+///
+/// Samples are splatted onto every pixel the reconstruction filter reaches, weighted by the filter
+/// Each pixel also keeps statistics of the samples taken inside it, to tell how converged it is, and its AOVs
+pub struct Film {
+    width: u32,
+    height: u32,
+    filter: Filter,
+    /// Row-major, row 0 is the top row of the image
+    weighted_radiance: Vec<Color>,
+    weights: Vec<f32>,
+    statistics: Vec<PixelStatistics>,
+    aovs: Vec<AovPixel>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Self {
+        let num_pixels = (width * height) as usize;
+        Film {
+            width: width,
+            height: height,
+            filter: filter,
+            weighted_radiance: vec![Color::black(); num_pixels],
+            weights: vec![0.0; num_pixels],
+            statistics: vec![PixelStatistics::default(); num_pixels],
+            aovs: vec![AovPixel::default(); num_pixels],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Adds a sample taken inside pixel (x, y) at offset (0..1, 0..1) from the pixel's bottom-left corner
+    /// Pixel (x, y) covers [x, x + 1) * [y, y + 1), and its center is at (x + 0.5, y + 0.5)
+    ///
+    /// The pixel is passed in rather than derived from the position: x + offset can round up to x + 1 in f32
+    pub fn add_sample(&mut self, x: u32, y: u32, offset: (f32, f32), color: &Color) {
+        let owner = self.index(x, y);
+        self.statistics[owner].add(luminance(color));
+
+        let film_x = x as f32 + offset.0;
+        let film_y = y as f32 + offset.1;
+
+        let radius = self.filter.radius();
+        // the range of pixels whose centers lie within the filter radius
+        let min_x = (film_x - 0.5 - radius).ceil().max(0.0) as u32;
+        let max_x = (film_x - 0.5 + radius).floor().min(self.width as f32 - 1.0);
+        let min_y = (film_y - 0.5 - radius).ceil().max(0.0) as u32;
+        let max_y = (film_y - 0.5 + radius).floor().min(self.height as f32 - 1.0);
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+
+        for y in min_y..=max_y as u32 {
+            for x in min_x..=max_x as u32 {
+                let weight = self.filter.evaluate(x as f32 + 0.5 - film_x, y as f32 + 0.5 - film_y);
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let index = self.index(x, y);
+                self.weighted_radiance[index] += color * weight;
+                self.weights[index] += weight;
+            }
+        }
+    }
+
+    /// The filtered radiance of pixel (x, y), where y = 0 is the bottom row
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = self.index(x, y);
+        if self.weights[index] == 0.0 {
+            return Color::black();
+        }
+
+        return &self.weighted_radiance[index] / self.weights[index];
+    }
+
+    /// Statistics of the samples taken inside pixel (x, y), where y = 0 is the bottom row
+    pub fn statistics(&self, x: u32, y: u32) -> &PixelStatistics {
+        &self.statistics[self.index(x, y)]
+    }
+
+    /// Writes the film as a plain-text PPM (P3), top row first
+    pub fn write_ppm<W: Write>(&self, post_process: &PostProcess, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "P3\n{} {}\n255", self.width, self.height)?;
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let [r, g, b] = post_process.apply(&self.pixel(x, y));
+                writeln!(writer, "{} {} {}", r, g, b)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Writes the film as a PPM file
+    /// The image goes to a temporary file first and is then renamed over the destination, so a render
+    /// that gets killed while writing leaves the previous image intact instead of half a file
+    pub fn save_ppm<P: AsRef<Path>>(&self, post_process: &PostProcess, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        self.write_ppm(post_process, &mut writer)?;
+        writer.into_inner()?.sync_all()?;
+        return fs::rename(&temporary_path, path);
+    }
+
+    /// Writes how many samples each pixel took as a PPM, from black (none) through blue and red to white (max_samples)
+    pub fn write_sample_heatmap<W: Write>(&self, max_samples: u32, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "P3\n{} {}\n255", self.width, self.height)?;
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let t = (self.statistics(x, y).count() as f32 / max_samples.max(1) as f32).min(1.0);
+                // piecewise-linear black => blue => red => white ramp
+                let (r, g, b) = if t < 1.0 / 3.0 {
+                    (0.0, 0.0, 3.0 * t)
+                } else if t < 2.0 / 3.0 {
+                    (3.0 * t - 1.0, 0.0, 2.0 - 3.0 * t)
+                } else {
+                    (1.0, 3.0 * t - 2.0, 3.0 * t - 2.0)
+                };
+                writeln!(writer, "{} {} {}", (255.0 * r) as u8, (255.0 * g) as u8, (255.0 * b) as u8)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((self.height - 1 - y) * self.width + x) as usize
+    }
+}
+
+/// The perceived brightness of a linear sRGB color (Rec. 709 weights)
+pub fn luminance(color: &Color) -> f32 {
+    0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn film_counts_samples_in_their_own_pixel() {
+        let mut film = Film::new(8, 2, Filter::default());
+
+        // 5.0 + 0.99999994 rounds to 6.0 in f32
+        film.add_sample(5, 1, (0.999_999_94, 0.5), &Color::white());
+
+        assert_eq!(1, film.statistics(5, 1).count());
+        assert_eq!(0, film.statistics(6, 1).count());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::Color;
+use crate::ToneMapper;
+
+/// Turns linear radiance from the Film into 8-bit sRGB pixels
+///
+/// exposure (in stops) => tone mapping => sRGB transfer function => clamp and quantize
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcess {
+    /// Each stop doubles the brightness, so 0.0 leaves the radiance alone and -1.0 halves it
+    pub exposure: f32,
+    pub tone_mapper: ToneMapper,
+}
+
+impl PostProcess {
+    pub fn new(exposure: f32, tone_mapper: ToneMapper) -> Self {
+        PostProcess {
+            exposure: exposure,
+            tone_mapper: tone_mapper,
+        }
+    }
+
+    /// Applies the exposure and tone mapping, the result is still linear
+    pub fn tone_map(&self, radiance: &Color) -> Color {
+        let exposed = radiance * 2f32.powf(self.exposure);
+        return self.tone_mapper.map(&exposed);
+    }
+
+    pub fn apply(&self, radiance: &Color) -> [u8; 3] {
+        let mapped = self.tone_map(radiance);
+        return [quantize(mapped.r()), quantize(mapped.g()), quantize(mapped.b())];
+    }
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        PostProcess::new(0.0, ToneMapper::Clamp)
+    }
+}
+
+/// The sRGB opto-electronic transfer function, linear => display encoded
+/// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        return 12.92 * linear;
+    }
+
+    return 1.055 * linear.powf(1.0 / 2.4) - 0.055;
+}
+
+/// The inverse of linear_to_srgb, display encoded => linear
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        return encoded / 12.92;
+    }
+
+    return ((encoded + 0.055) / 1.055).powf(2.4);
+}
+
+/// Encodes a linear value into 8 bits, clamping anything outside of [0, 1] (NaN becomes 0)
+fn quantize(linear: f32) -> u8 {
+    let encoded = linear_to_srgb(linear);
+    if encoded.is_nan() {
+        return 0;
+    }
+
+    return (255.0 * encoded.clamp(0.0, 1.0)).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postprocess_clamps_bright_values() {
+        let post_process = PostProcess::default();
+
+        assert_eq!([255, 255, 255], post_process.apply(&Color::new(1.0, 40.0, 1e9)));
+        assert_eq!([0, 0, 0], post_process.apply(&Color::new(-1.0, 0.0, f32::NAN)));
+        assert_eq!([188, 188, 188], post_process.apply(&Color::gray(0.5)));
+    }
+
+    #[test]
+    fn postprocess_tone_mappers_stay_in_range() {
+        for tone_mapper in [ToneMapper::Reinhard, ToneMapper::AcesFilmic, ToneMapper::Agx].iter() {
+            let post_process = PostProcess::new(0.0, *tone_mapper);
+            let mut previous = 0.0;
+            for intensity in [0.01, 0.1, 1.0, 10.0, 100.0, 1000.0].iter() {
+                let mapped = post_process.tone_map(&Color::gray(*intensity));
+                assert!(mapped.g() <= 1.0, "{:?} mapped {} to {}", tone_mapper, intensity, mapped.g());
+                assert!(mapped.g() >= previous, "{:?} is not monotonic at {}", tone_mapper, intensity);
+                previous = mapped.g();
+            }
+        }
+    }
+
+    #[test]
+    fn postprocess_srgb_round_trips() {
+        for value in [0.0, 0.001, 0.2, 0.5, 1.0].iter() {
+            assert!((srgb_to_linear(linear_to_srgb(*value)) - value).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::Film;
+use crate::Filter;
+use crate::PixelStatistics;
+use crate::Color;
+use crate::Float;
+use crate::Vector3;
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies "the render" an accumulation state belongs to
+/// Samplers are pure functions of (pixel, sample index, seed), so the seeds plus each pixel's sample count
+/// are all the random state there is: resuming simply continues every pixel from its next sample index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSeeds {
+    /// The seed the scene was generated with, renders of different scenes can't be combined
+    pub scene_seed: u64,
+    pub sampler_seed: u64,
+    /// A hash of the scene file, camera, projection and filter (see RenderSettings::seeds)
+    /// Renders with different fingerprints are of different images, even when the scene seed matches
+    /// States from before version 3 didn't save one and load with 0, which matches no render
+    pub fingerprint: u64,
+}
+
+const MAGIC: &[u8; 8] = b"YARRTACC";
+const VERSION: u32 = 3;
+/// Bytes per pixel: radiance, weight and statistics, then (from version 2) the AOV sums
+/// Version 3 only added the fingerprint to the header, its pixels are the same as version 2's
+const VERSION_1_PIXEL_SIZE: usize = 7 * 4;
+const VERSION_2_PIXEL_SIZE: usize = VERSION_1_PIXEL_SIZE + 4 * 4 + 9 * 4 + 6 * 4 + 4;
+
+/// Saving, loading and merging the accumulation buffer of a Film
+///
+/// The file is little-endian binary: the magic bytes, a version, width and height, the seeds and fingerprint,
+/// then for every pixel (top row first) its weighted radiance (RGB), filter weight, sample statistics and AOV sums
+/// Version 1 files had no AOVs, they load with empty AOVs
+impl Film {
+    pub fn write_state<W: Write>(&self, seeds: &RenderSeeds, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&seeds.scene_seed.to_le_bytes())?;
+        writer.write_all(&seeds.sampler_seed.to_le_bytes())?;
+        writer.write_all(&seeds.fingerprint.to_le_bytes())?;
+        for index in 0..self.weights.len() {
+            let radiance = &self.weighted_radiance[index];
+            let statistics = &self.statistics[index];
+            for value in [radiance.r(), radiance.g(), radiance.b(), self.weights[index]] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&statistics.count().to_le_bytes())?;
+            writer.write_all(&statistics.mean().to_le_bytes())?;
+            writer.write_all(&statistics.m2().to_le_bytes())?;
+
+            let aov = &self.aovs[index];
+            writer.write_all(&aov.samples.to_le_bytes())?;
+            writer.write_all(&aov.hits.to_le_bytes())?;
+            writer.write_all(&aov.object_id.to_le_bytes())?;
+            writer.write_all(&aov.material_id.to_le_bytes())?;
+            for color in [&aov.albedo, &aov.direct, &aov.indirect] {
+                for value in [color.r(), color.g(), color.b()] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+            for vector in [&aov.normal, &aov.position] {
+                for value in [vector.x(), vector.y(), vector.z()] {
+                    writer.write_all(&(value as f32).to_le_bytes())?;
+                }
+            }
+            writer.write_all(&aov.depth.to_le_bytes())?;
+        }
+
+        return Ok(());
+    }
+
+    /// Reads a state written by write_state
+    /// The filter itself isn't saved, only its part of the fingerprint, so it has to be passed in again
+    pub fn read_state<R: Read>(reader: &mut R, filter: Filter) -> io::Result<(Film, RenderSeeds)> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a render state file"));
+        }
+
+        let version = read_u32(reader)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported render state version {}", version)));
+        }
+
+        let width = read_u32(reader)?;
+        let height = read_u32(reader)?;
+        let seeds = RenderSeeds {
+            scene_seed: read_u64(reader)?,
+            sampler_seed: read_u64(reader)?,
+            fingerprint: if version >= 3 { read_u64(reader)? } else { 0 },
+        };
+
+        // the header's size is only believed once the pixels are really there, a corrupt one could ask for any amount of memory
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+        let pixel_size = if version >= 2 { VERSION_2_PIXEL_SIZE } else { VERSION_1_PIXEL_SIZE };
+        let expected_size = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(pixel_size));
+        if expected_size != Some(payload.len()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("render state says it is {}x{} but has {} bytes of pixels", width, height, payload.len())));
+        }
+
+        let reader = &mut payload.as_slice();
+        let mut film = Film::new(width, height, filter);
+        for index in 0..film.weights.len() {
+            film.weighted_radiance[index] = Color::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+            film.weights[index] = read_f32(reader)?;
+            let count = read_u32(reader)?;
+            film.statistics[index] = PixelStatistics::from_parts(count, read_f32(reader)?, read_f32(reader)?);
+            if version >= 2 {
+                let aov = &mut film.aovs[index];
+                aov.samples = read_u32(reader)?;
+                aov.hits = read_u32(reader)?;
+                aov.object_id = read_u32(reader)?;
+                aov.material_id = read_u32(reader)?;
+                for color in [&mut aov.albedo, &mut aov.direct, &mut aov.indirect] {
+                    *color = Color::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+                }
+                for vector in [&mut aov.normal, &mut aov.position] {
+                    *vector = Vector3::new(read_f32(reader)? as Float, read_f32(reader)? as Float, read_f32(reader)? as Float);
+                }
+                aov.depth = read_f32(reader)?;
+            }
+        }
+
+        return Ok((film, seeds));
+    }
+
+    /// Saves the state to a file, atomically like save_ppm
+    pub fn save_state<P: AsRef<Path>>(&self, seeds: &RenderSeeds, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        self.write_state(seeds, &mut writer)?;
+        writer.into_inner()?.sync_all()?;
+        return fs::rename(&temporary_path, path);
+    }
+
+    pub fn load_state<P: AsRef<Path>>(path: P, filter: Filter) -> io::Result<(Film, RenderSeeds)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        return Film::read_state(&mut reader, filter);
+    }
+
+    /// Adds the samples of another, independent render of the same scene to this film
+    /// The renders must have used different sampler seeds, otherwise they traced the exact same samples
+    pub fn merge(&mut self, seeds: &RenderSeeds, other: &Film, other_seeds: &RenderSeeds) -> io::Result<()> {
+        if self.width != other.width || self.height != other.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't merge a {}x{} render into a {}x{} render", other.width, other.height, self.width, self.height)));
+        }
+
+        if seeds.scene_seed != other_seeds.scene_seed || seeds.fingerprint != other_seeds.fingerprint {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't merge renders of different scenes"));
+        }
+
+        if seeds.sampler_seed == other_seeds.sampler_seed {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't merge renders with the same sampler seed, they traced the same samples"));
+        }
+
+        for index in 0..self.weights.len() {
+            self.weighted_radiance[index] += &other.weighted_radiance[index];
+            self.weights[index] += other.weights[index];
+            self.statistics[index].merge(&other.statistics[index]);
+            self.aovs[index].merge(&other.aovs[index]);
+        }
+
+        return Ok(());
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    return Ok(u32::from_le_bytes(bytes));
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    return Ok(u64::from_le_bytes(bytes));
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    return Ok(f32::from_le_bytes(bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Aov, AovSample, AovSurface, Normal3, Point3};
+
+    #[test]
+    fn state_round_trips() {
+        let seeds = RenderSeeds { scene_seed: 1, sampler_seed: 2, fingerprint: 3 };
+        let mut film = Film::new(3, 2, Filter::default());
+        film.add_sample(1, 0, (0.5, 0.5), &Color::new(0.25, 0.5, 1.0));
+        film.add_sample(1, 0, (0.5, 0.5), &Color::new(0.75, 0.5, 1.0));
+        film.add_aov_sample(1, 0, &AovSample {
+            albedo: Color::gray(0.5),
+            direct: Color::new(0.25, 0.5, 1.0),
+            indirect: Color::black(),
+            surface: Some(AovSurface {
+                normal: Normal3::new(0.0, 1.0, 0.0),
+                depth: 2.0,
+                position: Point3::new(1.0, 2.0, 3.0),
+                object_id: 4,
+                material_id: 5,
+            }),
+        });
+
+        let mut bytes = Vec::new();
+        film.write_state(&seeds, &mut bytes).unwrap();
+        let (loaded, loaded_seeds) = Film::read_state(&mut bytes.as_slice(), Filter::default()).unwrap();
+
+        assert_eq!(seeds, loaded_seeds);
+        assert_eq!(film.pixel(1, 0), loaded.pixel(1, 0));
+        assert_eq!(film.statistics(1, 0), loaded.statistics(1, 0));
+        for aov in Aov::ALL.iter() {
+            assert_eq!(film.aov(*aov, 1, 0), loaded.aov(*aov, 1, 0));
+        }
+        assert!(Film::read_state(&mut &bytes[..bytes.len() - 1], Filter::default()).is_err());
+    }
+
+    #[test]
+    fn state_rejects_a_header_that_lies_about_its_size() {
+        let mut bytes = Vec::new();
+        Film::new(1, 1, Filter::default()).write_state(&RenderSeeds { scene_seed: 1, sampler_seed: 2, fingerprint: 3 }, &mut bytes).unwrap();
+        // claim a 100000x100000 film, with the pixels of a 1x1 one
+        bytes[12..16].copy_from_slice(&100_000u32.to_le_bytes());
+        bytes[16..20].copy_from_slice(&100_000u32.to_le_bytes());
+        let error = Film::read_state(&mut bytes.as_slice(), Filter::default()).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+        bytes[12..20].copy_from_slice(&[0xff; 8]);
+        assert_eq!(io::ErrorKind::InvalidData, Film::read_state(&mut bytes.as_slice(), Filter::default()).err().unwrap().kind());
+    }
+
+    #[test]
+    fn state_merges_independent_renders() {
+        let seeds = RenderSeeds { scene_seed: 1, sampler_seed: 2, fingerprint: 3 };
+        let other_seeds = RenderSeeds { scene_seed: 1, sampler_seed: 3, fingerprint: 3 };
+        let mut film = Film::new(1, 1, Filter::default());
+        let mut other = Film::new(1, 1, Filter::default());
+        film.add_sample(0, 0, (0.5, 0.5), &Color::white());
+        other.add_sample(0, 0, (0.5, 0.5), &Color::black());
+        other.add_sample(0, 0, (0.5, 0.5), &Color::black());
+
+        assert!(film.merge(&seeds, &other, &seeds).is_err());
+        assert!(film.merge(&seeds, &other, &RenderSeeds { fingerprint: 4, ..other_seeds }).is_err());
+        film.merge(&seeds, &other, &other_seeds).unwrap();
+
+        assert_eq!(3, film.statistics(0, 0).count());
+        assert!((film.pixel(0, 0).r() - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,103 @@
+/// Running statistics of the luminance of the samples taken in one pixel
+/// Uses Welford's online algorithm, so the variance is numerically stable without storing the samples
+/// https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Welford's_online_algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PixelStatistics {
+    count: u32,
+    mean: f32,
+    /// Sum of squared differences from the mean
+    m2: f32,
+}
+
+impl PixelStatistics {
+    pub fn add(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Combines the statistics of two sets of samples, as if all of them had been added to one
+    /// Chan et al.'s parallel variant of Welford's algorithm
+    pub fn merge(&mut self, other: &PixelStatistics) {
+        if other.count == 0 {
+            return;
+        }
+
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.m2 += other.m2 + delta * delta * (self.count as f32 * other.count as f32 / count as f32);
+        self.mean += delta * other.count as f32 / count as f32;
+        self.count = count;
+    }
+
+    pub(crate) fn from_parts(count: u32, mean: f32, m2: f32) -> Self {
+        PixelStatistics {
+            count: count,
+            mean: mean,
+            m2: m2,
+        }
+    }
+
+    pub(crate) fn m2(&self) -> f32 {
+        self.m2
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    /// The sample variance
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+
+        return self.m2 / (self.count - 1) as f32;
+    }
+
+    /// How far the mean is likely to be from the true pixel value, relative to the mean
+    /// This is the half-width of the 95% confidence interval divided by the mean
+    ///
+    /// Dark pixels are treated as if they had a luminance of at least 0.1, otherwise almost-black pixels
+    /// would soak up samples for noise nobody can see
+    pub fn relative_error(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+
+        let standard_error = (self.variance() / self.count as f32).sqrt();
+        return 1.96 * standard_error / self.mean.max(0.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_tracks_mean_and_variance() {
+        let mut statistics = PixelStatistics::default();
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0].iter() {
+            statistics.add(*value);
+        }
+
+        assert_eq!(8, statistics.count());
+        assert_eq!(5.0, statistics.mean());
+        assert!((statistics.variance() - 32.0 / 7.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn statistics_flat_pixels_have_no_error() {
+        let mut statistics = PixelStatistics::default();
+        for _ in 0..4 {
+            statistics.add(0.7);
+        }
+
+        assert_eq!(0.0, statistics.relative_error());
+    }
+}
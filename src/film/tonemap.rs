@@ -0,0 +1,105 @@
+use crate::Color;
+
+use super::luminance;
+
+/// Tone mappers squeeze unbounded linear radiance into the [0, 1] range a display can show
+/// All of them take and return linear values, the sRGB transfer function is applied afterwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapper {
+    /// No curve, anything above 1.0 is simply clamped
+    Clamp,
+    /// Reinhard on luminance: L / (1 + L), which keeps the hue of bright colors
+    Reinhard,
+    /// Stephen Hill's fit of the ACES reference rendering + output transforms
+    AcesFilmic,
+    /// Troy Sobotka's AgX (the Blender 4.0 default), using the polynomial fit of its base contrast curve
+    Agx,
+}
+
+impl ToneMapper {
+    pub fn map(&self, color: &Color) -> Color {
+        match self {
+            ToneMapper::Clamp => color.clone(),
+            ToneMapper::Reinhard => reinhard(color),
+            ToneMapper::AcesFilmic => aces_filmic(color),
+            ToneMapper::Agx => agx(color),
+        }
+    }
+}
+
+fn reinhard(color: &Color) -> Color {
+    let luminance = luminance(color);
+    if luminance <= 0.0 {
+        return Color::black();
+    }
+
+    return color * (1.0 / (1.0 + luminance));
+}
+
+/// Multiplies the row-major 3x3 matrix by the color
+fn transform(matrix: &[[f32; 3]; 3], color: &Color) -> Color {
+    Color::new(
+        matrix[0][0] * color.r() + matrix[0][1] * color.g() + matrix[0][2] * color.b(),
+        matrix[1][0] * color.r() + matrix[1][1] * color.g() + matrix[1][2] * color.b(),
+        matrix[2][0] * color.r() + matrix[2][1] * color.g() + matrix[2][2] * color.b(),
+    )
+}
+
+/// https://github.com/TheRealMJP/BakingLab/blob/master/BakingLab/ACES.hlsl
+fn aces_filmic(color: &Color) -> Color {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    const INPUT: [[f32; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    const OUTPUT: [[f32; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let fit = |x: f32| {
+        let a = x * (x + 0.0245786) - 0.000090537;
+        let b = x * (0.983729 * x + 0.432951) + 0.238081;
+        a / b
+    };
+
+    let color = transform(&INPUT, color);
+    let color = transform(&OUTPUT, &color.map(fit));
+    return color.map(|x| x.clamp(0.0, 1.0));
+}
+
+/// https://iolite-engine.com/blog_posts/minimal_agx_implementation
+fn agx(color: &Color) -> Color {
+    const INSET: [[f32; 3]; 3] = [
+        [0.84247906, 0.0784336, 0.07922375],
+        [0.04232824, 0.87846864, 0.07916613],
+        [0.04237565, 0.0784336, 0.879143],
+    ];
+    const OUTSET: [[f32; 3]; 3] = [
+        [1.196879, -0.09802088, -0.09902974],
+        [-0.05289685, 1.151903, -0.09896118],
+        [-0.05297164, -0.09804345, 1.151074],
+    ];
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let encode = |x: f32| {
+        // log2 encoding between MIN_EV and MAX_EV
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+
+        // 6th order polynomial fit of the AgX base contrast curve
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    };
+
+    let color = transform(&INSET, color);
+    let color = transform(&OUTSET, &color.map(encode));
+
+    // the curve produces display-encoded values, bring them back to linear for the sRGB stage
+    let linearize = |x: f32| x.max(0.0).powf(2.2);
+    return color.map(linearize);
+}
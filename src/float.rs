@@ -0,0 +1,103 @@
+//! The float type of the geometry: vectors, points, rays, intersections and cameras
+//!
+//! It is f32 unless the `f64` cargo feature is enabled, which helps huge scenes or tiny details far from the origin
+//! Colors, the film and the samplers stay f32 either way, they don't need the precision
+
+use crate::Normal3;
+use crate::Point3;
+use crate::Vector3;
+
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// PI and friends, as Float
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+/// The smallest Float above value
+pub fn next_float_up(value: Float) -> Float {
+    if value.is_infinite() && value > 0.0 {
+        return value;
+    }
+
+    // -0.0 and 0.0 both step up to the smallest positive float
+    let value = if value == 0.0 { 0.0 } else { value };
+    let bits = value.to_bits();
+    let bits = if value >= 0.0 { bits + 1 } else { bits - 1 };
+    return Float::from_bits(bits);
+}
+
+/// The largest Float below value
+pub fn next_float_down(value: Float) -> Float {
+    -next_float_up(-value)
+}
+
+/// A bound on the relative rounding error of n floating point operations in a row, (1 +- epsilon)^n - 1
+/// From Physically Based Rendering, section 3.9
+pub fn gamma(n: u32) -> Float {
+    let epsilon = Float::EPSILON * 0.5;
+    let n = n as Float;
+    return (n * epsilon) / (1.0 - n * epsilon);
+}
+
+/// Where a ray leaving a surface should start, so it can't hit the surface it leaves from again
+///
+/// The computed hit point is only within error (per axis) of the true surface, so the point is pushed along the
+/// geometric normal by the largest distance the error could put it on the wrong side, towards the side direction leaves from
+/// The offset point is rounded away from the surface too, or the rounding of the addition could undo the offset
+/// This is the approach of Physically Based Rendering, section 3.9.5, and replaces a fixed t_min epsilon:
+/// a fixed epsilon is too large for tiny details and too small for points far from the origin
+pub fn offset_ray_origin(point: &Point3, error: &Vector3, normal: &Normal3, direction: &Vector3) -> Point3 {
+    let distance = normal.x().abs() * error.x() + normal.y().abs() * error.y() + normal.z().abs() * error.z();
+    let mut offset = normal.as_vector() * distance;
+    if normal.dot(direction) < 0.0 {
+        offset = -offset;
+    }
+
+    let offset_point = point + &offset;
+    let round_away = |value: Float, offset: Float| {
+        if offset > 0.0 {
+            next_float_up(value)
+        } else if offset < 0.0 {
+            next_float_down(value)
+        } else {
+            value
+        }
+    };
+    return Point3::new(
+        round_away(offset_point.x(), offset.x()),
+        round_away(offset_point.y(), offset.y()),
+        round_away(offset_point.z(), offset.z()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_steps_to_neighbors() {
+        assert!(next_float_up(1.0) > 1.0);
+        assert!(next_float_down(1.0) < 1.0);
+        assert_eq!(1.0, next_float_down(next_float_up(1.0)));
+        assert!(next_float_up(-0.0) > 0.0);
+        assert!(next_float_down(0.0) < 0.0);
+    }
+
+    #[test]
+    fn float_offset_leaves_from_the_right_side() {
+        let point = Point3::new(1000.0, 0.1, -3.0);
+        let error = Vector3::new(1e-3, 1e-3, 1e-3);
+        let normal = Normal3::new(0.0, 1.0, 0.0);
+
+        let above = offset_ray_origin(&point, &error, &normal, &Vector3::new(1.0, 1.0, 0.0));
+        let below = offset_ray_origin(&point, &error, &normal, &Vector3::new(1.0, -1.0, 0.0));
+
+        assert!(above.y() > point.y() + 1e-3);
+        assert!(below.y() < point.y() - 1e-3);
+        assert_eq!(point.x(), above.x());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::Float;
+use crate::simd::{F32x4, Mask4, RayPacket4};
+use crate::Point3;
+use crate::Ray;
+
+/// An axis-aligned bounding box, a cheap test to skip everything inside it when a ray misses it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb {
+    min: Point3,
+    max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Aabb {
+            min: min,
+            max: max,
+        }
+    }
+
+    pub fn min(&self) -> &Point3 {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Point3 {
+        &self.max
+    }
+
+    /// The box around both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Point3::new(self.min.x().min(other.min.x()), self.min.y().min(other.min.y()), self.min.z().min(other.min.z())),
+            max: Point3::new(self.max.x().max(other.max.x()), self.max.y().max(other.max.y()), self.max.z().max(other.max.z())),
+        }
+    }
+
+    /// The slab test: the ray is inside the box where it is between the min and max planes on all three axes at once
+    pub fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let (origin, direction, min, max) = match axis {
+                0 => (ray.origin().x(), ray.direction().x(), self.min.x(), self.max.x()),
+                1 => (ray.origin().y(), ray.direction().y(), self.min.y(), self.max.y()),
+                _ => (ray.origin().z(), ray.direction().z(), self.min.z(), self.max.z()),
+            };
+
+            let inverse_direction = 1.0 / direction;
+            let near = (min - origin) * inverse_direction;
+            let far = (max - origin) * inverse_direction;
+            t_min = t_min.max(near.min(far));
+            t_max = t_max.min(near.max(far));
+            if t_max < t_min {
+                return false;
+            }
+        }
+
+        return true;
+    }
+
+    /// The slab test for four rays at once, the mask is set for the rays that hit the box
+    /// Only the intersection benchmark calls this, BVH traversal in the renderer uses hit()
+    pub fn hit_packet(&self, packet: &RayPacket4, t_min: F32x4, t_max: F32x4) -> Mask4 {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        let axes = [
+            (packet.origin.x, packet.inverse_direction.x, self.min.x(), self.max.x()),
+            (packet.origin.y, packet.inverse_direction.y, self.min.y(), self.max.y()),
+            (packet.origin.z, packet.inverse_direction.z, self.min.z(), self.max.z()),
+        ];
+        for (origin, inverse_direction, min, max) in axes {
+            let near = (F32x4::splat(min as f32) - origin) * inverse_direction;
+            let far = (F32x4::splat(max as f32) - origin) * inverse_direction;
+            t_min = t_min.max(near.min(far));
+            t_max = t_max.min(near.max(far));
+        }
+
+        return t_min.le(t_max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector3;
+
+    #[test]
+    fn aabb_packet_agrees_with_scalar() {
+        let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let rays = [
+            Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+            Ray::new(Point3::new(-5.0, 2.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+            Ray::new(Point3::new(-5.0, -5.0, -5.0), Vector3::new(1.0, 1.0, 1.0)),
+            Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+        ];
+        let packet = RayPacket4::new([&rays[0], &rays[1], &rays[2], &rays[3]]);
+        let mask = aabb.hit_packet(&packet, F32x4::splat(0.001), F32x4::splat(f32::MAX));
+
+        for (lane, ray) in rays.iter().enumerate() {
+            assert_eq!(aabb.hit(ray, 0.001, Float::MAX), mask.lane(lane), "lane {}", lane);
+        }
+        assert_eq!(0b0101, mask.bits());
+    }
+}
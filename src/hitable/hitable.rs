@@ -1,8 +1,29 @@
+use crate::Float;
 use crate::ray::Ray;
+use crate::simd::RayPacket4;
+use crate::Aabb;
 use crate::HitRecord;
 
 /// Base trait for defining "Hittable" items
 pub trait Hitable {
     /// Determines if the ray will hit this "Hittable" given a range of T
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    /// Hits on transparent parts of cut-out materials are skipped, they are never returned as the closest hit
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>>;
+
+    /// Hits four rays at once, each with its own t_max. Not used by the renderer, see crate::simd
+    /// Where this hitable is hit before t_max, the hit replaces the one in hits and t_max moves up to it, other lanes are left alone
+    /// Gives the same hits as calling hit() for every ray, which is what it does unless the hitable has a faster way
+    fn hit_packet<'a>(&'a self, packet: &RayPacket4, t_min: Float, t_max: &mut [Float; 4], hits: &mut [Option<HitRecord<'a>>; 4]) {
+        for lane in 0..4 {
+            if let Some(hit_record) = self.hit(packet.rays[lane], t_min, t_max[lane]) {
+                t_max[lane] = hit_record.hit_at;
+                hits[lane] = Some(hit_record);
+            }
+        }
+    }
+
+    /// A box around everything this hitable could hit, None if it's unbounded
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
 }
\ No newline at end of file
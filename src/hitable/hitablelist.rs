@@ -1,29 +1,80 @@
+use crate::Float;
 use crate::ray::Ray;
+use crate::simd::RayPacket4;
+use crate::Aabb;
 use crate::Hitable;
 use crate::HitRecord;
+use crate::PunctualLight;
 
+/// A list of hitables, hits are tagged with the index of the hitable (the object ID) and its material ID
+/// Also holds the scene's punctual lights, which can't be hit but light the hitables
 pub struct HitableList {
-    hitables: Vec<Box<Hitable>>,
+    hitables: Vec<Box<dyn Hitable>>,
+    material_ids: Vec<u32>,
+    lights: Vec<PunctualLight>,
 }
 
 impl HitableList {
-    pub fn new(hitables: Vec<Box<Hitable>>) -> HitableList {
+    /// Every hitable gets its own material ID, the same as its object ID
+    pub fn new(hitables: Vec<Box<dyn Hitable>>) -> HitableList {
+        let material_ids = (1..=hitables.len() as u32).collect();
+        HitableList::with_material_ids(hitables, material_ids)
+    }
+
+    /// Hitables made of the same material can share a material ID, so they show up as one in the material ID pass
+    pub fn with_material_ids(hitables: Vec<Box<dyn Hitable>>, material_ids: Vec<u32>) -> HitableList {
+        assert_eq!(hitables.len(), material_ids.len(), "every hitable needs a material ID");
         HitableList {
-            hitables: hitables
+            hitables: hitables,
+            material_ids: material_ids,
+            lights: Vec::new(),
         }
     }
+
+    pub fn with_lights(mut self, lights: Vec<PunctualLight>) -> HitableList {
+        self.lights = lights;
+        return self;
+    }
+
+    pub fn lights(&self) -> &[PunctualLight] {
+        &self.lights
+    }
 }
 
 impl Hitable for HitableList {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         let mut t_max = t_max;
         let mut closest_hit: Option<HitRecord> = None;
-        for hitable in self.hitables.iter() {
-            if let Some(hit_record) = hitable.hit(ray, t_min, t_max) {
+        for (index, hitable) in self.hitables.iter().enumerate() {
+            if let Some(mut hit_record) = hitable.hit(ray, t_min, t_max) {
                 t_max = hit_record.hit_at;
+                hit_record.object_id = index as u32 + 1;
+                hit_record.material_id = self.material_ids[index];
                 closest_hit = Some(hit_record);
             }
         }
         return closest_hit;
     }
+
+    fn hit_packet<'a>(&'a self, packet: &RayPacket4, t_min: Float, t_max: &mut [Float; 4], hits: &mut [Option<HitRecord<'a>>; 4]) {
+        for (index, hitable) in self.hitables.iter().enumerate() {
+            let previous_t_max = *t_max;
+            hitable.hit_packet(packet, t_min, t_max, hits);
+            for lane in 0..4 {
+                // t_max only moves when the hitable replaced the hit
+                if t_max[lane] != previous_t_max[lane] {
+                    if let Some(hit_record) = &mut hits[lane] {
+                        hit_record.object_id = index as u32 + 1;
+                        hit_record.material_id = self.material_ids[index];
+                    }
+                }
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.hitables.iter().map(|hitable| hitable.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |union, bounding_box| Some(union.union(&bounding_box?)))
+    }
 }
\ No newline at end of file
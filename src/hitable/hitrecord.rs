@@ -1,22 +1,64 @@
+use crate::float;
+use crate::Color;
+use crate::Float;
 use crate::Material;
+use crate::Normal3;
+use crate::Point3;
+use crate::Ray;
 use crate::Vector3;
 
+#[derive(Clone)]
 pub struct HitRecord<'a> {
-    pub hit_at: f32,
-    pub point: Vector3,
-    pub normal: Vector3,
-    pub material: Box<&'a dyn Material>,
+    pub hit_at: Float,
+    pub point: Point3,
+    /// How far (per axis) point may be from the true surface due to rounding, see spawn_ray
+    pub point_error: Vector3,
+    /// The shading normal. Materials scatter around this one, and it may be perturbed by a normal or bump map
+    pub normal: Normal3,
+    /// The true normal of the geometry that was hit, never perturbed
+    pub geometric_normal: Normal3,
+    /// Surface coordinates of the hit, used for texture lookups
+    pub u: Float,
+    pub v: Float,
+    /// How the point moves as u increases (dP/du), lies in the surface
+    pub tangent: Vector3,
+    /// How the point moves as v increases (dP/dv), lies in the surface
+    pub bitangent: Vector3,
+    /// The material of the object that was hit, borrowed from it
+    pub material: &'a dyn Material,
+    /// The color painted on the surface at the hit, for meshes with per-vertex colors
+    pub vertex_color: Option<Color>,
+    /// Which object of the HitableList was hit, starting at 1. 0 until the HitableList fills it in
+    pub object_id: u32,
+    /// Objects sharing a material ID share a material, 0 until the HitableList fills it in
+    pub material_id: u32,
 }
 
 /// Represents a Ray Hit Record
 impl<'a> HitRecord<'a> {
     // Creates a new Ray Hit Record
-    pub fn new(hit_at: f32, point: Vector3, normal: Vector3, material: Box<&'a dyn Material>) -> Self {
+    // The shading normal starts out as the geometric normal
+    pub fn new(hit_at: Float, point: Point3, normal: Normal3, uv: (Float, Float), tangent: Vector3, bitangent: Vector3, material: &'a dyn Material) -> Self {
         HitRecord {
             hit_at: hit_at,
             point: point,
+            point_error: Vector3::new(0.0, 0.0, 0.0),
+            geometric_normal: normal.clone(),
             normal: normal,
+            u: uv.0,
+            v: uv.1,
+            tangent: tangent,
+            bitangent: bitangent,
             material: material,
+            vertex_color: None,
+            object_id: 0,
+            material_id: 0,
         }
     }
+
+    /// A ray leaving the surface in direction, starting just far enough off the surface that it can't hit it again
+    pub fn spawn_ray(&self, direction: Vector3) -> Ray {
+        let origin = float::offset_ray_origin(&self.point, &self.point_error, &self.geometric_normal, &direction);
+        return Ray::new(origin, direction);
+    }
 }
@@ -0,0 +1,411 @@
+use crate::float;
+use crate::Float;
+use crate::Aabb;
+use crate::Color;
+use crate::Frame;
+use crate::Hitable;
+use crate::HitRecord;
+use crate::Material;
+use crate::Normal3;
+use crate::Point3;
+use crate::Ray;
+use crate::Vector3;
+
+use std::io;
+use std::sync::Arc;
+
+/// The vertices and triangles of a mesh, shared by every Mesh placed from it
+/// Triangles are counterclockwise seen from the front, which is the side the geometric normal points to
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    /// Three vertex indices per triangle, the same index into every vertex attribute
+    indices: Vec<[u32; 3]>,
+    /// Per-vertex shading normals, for smooth shading
+    normals: Option<Vec<Normal3>>,
+    /// Per-vertex texture coordinates, v runs bottom to top like ImageTexture's
+    uvs: Option<Vec<(Float, Float)>>,
+    /// Per-vertex colors, which materials can use as an albedo (see HitRecord::vertex_color)
+    colors: Option<Vec<Color>>,
+}
+
+impl TriangleMesh {
+    /// Fails when a triangle refers to a vertex that isn't there
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>) -> io::Result<Self> {
+        if let Some(triangle) = indices.iter().find(|triangle| triangle.iter().any(|&index| index as usize >= positions.len())) {
+            return Err(invalid_data(format!("triangle {:?} refers to a vertex past the {} vertices of the mesh", triangle, positions.len())));
+        }
+
+        Ok(TriangleMesh {
+            positions: positions,
+            indices: indices,
+            normals: None,
+            uvs: None,
+            colors: None,
+        })
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Normal3>) -> io::Result<Self> {
+        self.check_vertex_count("normals", normals.len())?;
+        self.normals = Some(normals);
+        return Ok(self);
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(Float, Float)>) -> io::Result<Self> {
+        self.check_vertex_count("texture coordinates", uvs.len())?;
+        self.uvs = Some(uvs);
+        return Ok(self);
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> io::Result<Self> {
+        self.check_vertex_count("colors", colors.len())?;
+        self.colors = Some(colors);
+        return Ok(self);
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    pub fn normals(&self) -> Option<&[Normal3]> {
+        self.normals.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[(Float, Float)]> {
+        self.uvs.as_deref()
+    }
+
+    pub fn colors(&self) -> Option<&[Color]> {
+        self.colors.as_deref()
+    }
+
+    fn check_vertex_count(&self, attribute: &str, count: usize) -> io::Result<()> {
+        if count != self.positions.len() {
+            return Err(invalid_data(format!("the mesh has {} vertices but {} {}", self.positions.len(), count, attribute)));
+        }
+        return Ok(());
+    }
+
+    fn vertices(&self, triangle: usize) -> [&Point3; 3] {
+        let [a, b, c] = self.indices[triangle];
+        [&self.positions[a as usize], &self.positions[b as usize], &self.positions[c as usize]]
+    }
+
+    fn bounds(&self, triangle: usize) -> Aabb {
+        let [a, b, c] = self.vertices(triangle);
+        Aabb::new(a.clone(), a.clone()).union(&Aabb::new(b.clone(), b.clone())).union(&Aabb::new(c.clone(), c.clone()))
+    }
+}
+
+/// A triangle mesh made of one material, with a bounding volume hierarchy (BVH) over its triangles
+/// so a ray only tests the few triangles whose boxes it passes through
+pub struct Mesh {
+    mesh: Arc<TriangleMesh>,
+    material: Arc<dyn Material>,
+    /// nodes[0] is the root
+    nodes: Vec<BvhNode>,
+    /// Triangle indices, in the order the leaves refer to them
+    triangles: Vec<u32>,
+}
+
+struct BvhNode {
+    bounds: Aabb,
+    /// Leaves: where their triangles start in Mesh::triangles
+    /// Interior nodes: the index of the second child, the first child is the node right after this one
+    offset: u32,
+    /// The number of triangles of a leaf, 0 for interior nodes
+    count: u32,
+}
+
+const MAX_TRIANGLES_PER_LEAF: usize = 4;
+
+impl Mesh {
+    pub fn new(mesh: Arc<TriangleMesh>, material: Arc<dyn Material>) -> Self {
+        let triangle_count = mesh.indices.len();
+        let bounds: Vec<Aabb> = (0..triangle_count).map(|triangle| mesh.bounds(triangle)).collect();
+        let centroids: Vec<Point3> = bounds.iter()
+            .map(|bounds| Point3::from_vector((bounds.min().as_vector() + bounds.max().as_vector()) * 0.5))
+            .collect();
+
+        let mut built = Mesh {
+            mesh: mesh,
+            material: material,
+            nodes: Vec::new(),
+            triangles: (0..triangle_count as u32).collect(),
+        };
+        if triangle_count > 0 {
+            built.build_node(&bounds, &centroids, 0, triangle_count);
+        }
+        return built;
+    }
+
+    /// Builds the node over triangles[start..end], splitting at the median centroid along the widest axis
+    /// Returns the index of the node
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Point3], start: usize, end: usize) -> usize {
+        let triangles = &mut self.triangles[start..end];
+        let node_bounds = triangles.iter().skip(1)
+            .fold(bounds[triangles[0] as usize].clone(), |union, &triangle| union.union(&bounds[triangle as usize]));
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode { bounds: node_bounds, offset: start as u32, count: (end - start) as u32 });
+        if end - start <= MAX_TRIANGLES_PER_LEAF {
+            return index;
+        }
+
+        let coordinate = |point: &Point3, axis: usize| [point.x(), point.y(), point.z()][axis];
+        let centroid_bounds = triangles.iter().skip(1).fold(Aabb::new(centroids[triangles[0] as usize].clone(), centroids[triangles[0] as usize].clone()), |union, &triangle| {
+            let centroid = &centroids[triangle as usize];
+            union.union(&Aabb::new(centroid.clone(), centroid.clone()))
+        });
+        let extent = centroid_bounds.max() - centroid_bounds.min();
+        let axis = if extent.x() >= extent.y() && extent.x() >= extent.z() { 0 } else if extent.y() >= extent.z() { 1 } else { 2 };
+        if coordinate(centroid_bounds.max(), axis) <= coordinate(centroid_bounds.min(), axis) {
+            // every centroid is in the same spot, there's no splitting them
+            return index;
+        }
+
+        let middle = (end - start) / 2;
+        triangles.select_nth_unstable_by(middle, |&lhs, &rhs| {
+            coordinate(&centroids[lhs as usize], axis).total_cmp(&coordinate(&centroids[rhs as usize], axis))
+        });
+
+        self.nodes[index].count = 0;
+        self.build_node(bounds, centroids, start, start + middle);
+        let second = self.build_node(bounds, centroids, start + middle, end);
+        self.nodes[index].offset = second as u32;
+        return index;
+    }
+
+    /// Möller-Trumbore, hits both the front and the back of the triangle
+    fn hit_triangle(&self, triangle: usize, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.mesh.vertices(triangle);
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+        let p = ray.direction().cross(&edge2);
+        let determinant = Vector3::dot(&edge1, &p);
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let to_origin = ray.origin() - p0;
+        let b1 = Vector3::dot(&to_origin, &p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = to_origin.cross(&edge1);
+        let b2 = Vector3::dot(ray.direction(), &q) * inverse_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let time_at_hit = Vector3::dot(&edge2, &q) * inverse_determinant;
+        if !(t_min < time_at_hit && time_at_hit < t_max) {
+            return None;
+        }
+
+        let barycentrics = [1.0 - b1 - b2, b1, b2];
+        let hit_record = self.hit_record_at(triangle, time_at_hit, barycentrics);
+        if hit_record.material.is_transparent_at(&hit_record) {
+            return None;
+        }
+        return Some(hit_record);
+    }
+
+    fn hit_record_at(&self, triangle: usize, time_at_hit: Float, barycentrics: [Float; 3]) -> HitRecord<'_> {
+        let [a, b, c] = self.mesh.indices[triangle];
+        let vertices = [a as usize, b as usize, c as usize];
+        let positions = self.mesh.vertices(triangle);
+
+        // the point from the barycentrics instead of the ray, it's closer to the triangle (Physically Based Rendering, section 3.9.4)
+        let weighted: Vec<Vector3> = (0..3).map(|corner| positions[corner].as_vector() * barycentrics[corner]).collect();
+        let point = Point3::from_vector(&weighted[0] + &weighted[1] + &weighted[2]);
+        let error = |axis: fn(&Vector3) -> Float| float::gamma(7) * weighted.iter().map(|vector| axis(vector).abs()).sum::<Float>();
+        let point_error = Vector3::new(error(Vector3::x), error(Vector3::y), error(Vector3::z));
+
+        let edge1 = positions[1] - positions[0];
+        let edge2 = positions[2] - positions[0];
+        let mut geometric_normal = Normal3::from_vector(&edge1.cross(&edge2));
+        let shading_normal = self.mesh.normals.as_ref().and_then(|normals| {
+            let interpolated = (0..3).fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| sum + normals[vertices[corner]].as_vector() * barycentrics[corner]);
+            if interpolated.magnitude_squared() > 0.0 { Some(Normal3::from_vector(&interpolated)) } else { None }
+        });
+        if let Some(shading_normal) = &shading_normal {
+            // the winding and the vertex normals can disagree, the vertex normals are what the artist meant
+            geometric_normal = geometric_normal.facing(shading_normal.as_vector());
+        }
+
+        let uvs = match &self.mesh.uvs {
+            Some(uvs) => [uvs[vertices[0]], uvs[vertices[1]], uvs[vertices[2]]],
+            None => [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+        };
+        let uv = (
+            uvs[0].0 * barycentrics[0] + uvs[1].0 * barycentrics[1] + uvs[2].0 * barycentrics[2],
+            uvs[0].1 * barycentrics[0] + uvs[1].1 * barycentrics[1] + uvs[2].1 * barycentrics[2]);
+        let (tangent, bitangent) = surface_tangents(positions, uvs, &geometric_normal);
+
+        let mut hit_record = HitRecord::new(time_at_hit, point, geometric_normal, uv, tangent, bitangent, &*self.material);
+        hit_record.point_error = point_error;
+        if let Some(shading_normal) = shading_normal {
+            hit_record.normal = shading_normal;
+        }
+        if let Some(colors) = &self.mesh.colors {
+            hit_record.vertex_color = Some(
+                &(&(&colors[vertices[0]] * barycentrics[0] as f32) + &(&colors[vertices[1]] * barycentrics[1] as f32)) + &(&colors[vertices[2]] * barycentrics[2] as f32));
+        }
+        return hit_record;
+    }
+}
+
+/// dP/du and dP/dv of the triangle from its texture coordinates
+/// Any two tangents perpendicular to the normal when the texture coordinates don't span an area
+fn surface_tangents(positions: [&Point3; 3], uvs: [(Float, Float); 3], normal: &Normal3) -> (Vector3, Vector3) {
+    let (du02, dv02) = (uvs[0].0 - uvs[2].0, uvs[0].1 - uvs[2].1);
+    let (du12, dv12) = (uvs[1].0 - uvs[2].0, uvs[1].1 - uvs[2].1);
+    let determinant = du02 * dv12 - dv02 * du12;
+    if determinant.abs() > 1e-9 {
+        let dp02 = positions[0] - positions[2];
+        let dp12 = positions[1] - positions[2];
+        let tangent = (&dp02 * dv12 - &dp12 * dv02) / determinant;
+        let bitangent = (&dp12 * du02 - &dp02 * du12) / determinant;
+        if tangent.cross(&bitangent).magnitude_squared() > 0.0 {
+            return (tangent, bitangent);
+        }
+    }
+
+    let frame = Frame::from_normal(normal);
+    return (frame.to_world(&Vector3::new(1.0, 0.0, 0.0)), frame.to_world(&Vector3::new(0.0, 1.0, 0.0)));
+}
+
+/// The slab test of Aabb::hit, but with the far distances rounded up a little, so a ray can't slip
+/// between the boxes of neighboring triangles, or past a flat box, by a rounding error (Physically Based Rendering, section 3.9.2)
+fn hits_bounds(bounds: &Aabb, ray: &Ray, inverse_direction: &[Float; 3], t_min: Float, t_max: Float) -> bool {
+    let (mut t_min, mut t_max) = (t_min, t_max);
+    let (origin, min, max) = (ray.origin(), bounds.min(), bounds.max());
+    let axes = [(origin.x(), min.x(), max.x()), (origin.y(), min.y(), max.y()), (origin.z(), min.z(), max.z())];
+    for (axis, (origin, min, max)) in axes.iter().enumerate() {
+        let near = (min - origin) * inverse_direction[axis];
+        let far = (max - origin) * inverse_direction[axis];
+        let (near, far) = if near <= far { (near, far) } else { (far, near) };
+        t_min = t_min.max(near);
+        t_max = t_max.min(far * (1.0 + 2.0 * float::gamma(3)));
+        if t_max < t_min {
+            return false;
+        }
+    }
+    return true;
+}
+
+impl Hitable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let direction = ray.direction();
+        let inverse_direction = [1.0 / direction.x(), 1.0 / direction.y(), 1.0 / direction.z()];
+        let mut t_max = t_max;
+        let mut closest_hit: Option<HitRecord> = None;
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !hits_bounds(&node.bounds, ray, &inverse_direction, t_min, t_max) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.offset as usize);
+                stack.push(index + 1);
+                continue;
+            }
+
+            let start = node.offset as usize;
+            for &triangle in &self.triangles[start..start + node.count as usize] {
+                if let Some(hit_record) = self.hit_triangle(triangle as usize, ray, t_min, t_max) {
+                    t_max = hit_record.hit_at;
+                    closest_hit = Some(hit_record);
+                }
+            }
+        }
+
+        return closest_hit;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|root| root.bounds.clone())
+    }
+}
+
+pub(super) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LambertarianMaterial;
+    use crate::sampler::hashing::Pcg32;
+
+    /// A bumpy grid of triangles, enough of them for a BVH a few levels deep
+    fn grid(size: u32) -> TriangleMesh {
+        let mut positions = Vec::new();
+        for z in 0..=size {
+            for x in 0..=size {
+                positions.push(Point3::new(x as Float, ((x * 7 + z * 3) % 5) as Float * 0.2, z as Float));
+            }
+        }
+
+        let vertex = |x: u32, z: u32| z * (size + 1) + x;
+        let mut indices = Vec::new();
+        for z in 0..size {
+            for x in 0..size {
+                indices.push([vertex(x, z), vertex(x, z + 1), vertex(x + 1, z)]);
+                indices.push([vertex(x + 1, z), vertex(x, z + 1), vertex(x + 1, z + 1)]);
+            }
+        }
+        return TriangleMesh::new(positions, indices).unwrap();
+    }
+
+    #[test]
+    fn mesh_bvh_finds_the_same_hits_as_testing_every_triangle() {
+        let mesh = Mesh::new(Arc::new(grid(12)), Arc::new(LambertarianMaterial::new(Color::gray(0.5))));
+        let mut rng = Pcg32::new(3, 1);
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = Point3::new(rng.next_f32() as Float * 10.0 + 1.0, 3.0, rng.next_f32() as Float * 10.0 + 1.0);
+            let direction = Vector3::new(rng.next_f32() as Float - 0.5, -1.0, rng.next_f32() as Float - 0.5);
+            let ray = Ray::new(origin, direction);
+
+            let brute_force = (0..mesh.mesh.indices.len())
+                .filter_map(|triangle| mesh.hit_triangle(triangle, &ray, 0.0, Float::MAX))
+                .map(|hit_record| hit_record.hit_at)
+                .fold(None, |closest: Option<Float>, hit_at| Some(closest.map_or(hit_at, |closest| closest.min(hit_at))));
+            assert_eq!(brute_force, mesh.hit(&ray, 0.0, Float::MAX).map(|hit_record| hit_record.hit_at));
+            hits += brute_force.is_some() as u32;
+        }
+        assert!(hits > 450, "{}", hits);
+    }
+
+    #[test]
+    fn mesh_interpolates_vertex_attributes() {
+        let positions = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+        let triangle = TriangleMesh::new(positions, vec![[0, 1, 2]]).unwrap()
+            .with_colors(vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)]).unwrap()
+            .with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).unwrap();
+        let mesh = Mesh::new(Arc::new(triangle), Arc::new(LambertarianMaterial::new(Color::gray(0.5))));
+
+        let ray = Ray::new(Point3::new(0.25, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit_record = mesh.hit(&ray, 0.0, Float::MAX).unwrap();
+        assert!((hit_record.u - 0.25).abs() < 1e-5 && (hit_record.v - 0.5).abs() < 1e-5);
+        assert!((hit_record.normal.z() - 1.0).abs() < 1e-5);
+        let color = hit_record.vertex_color.unwrap();
+        assert!((color.r() - 0.25).abs() < 1e-5 && (color.g() - 0.25).abs() < 1e-5 && (color.b() - 0.5).abs() < 1e-5);
+        assert!((hit_record.tangent.x() - 1.0).abs() < 1e-5 && (hit_record.bitangent.y() - 1.0).abs() < 1e-5);
+
+        assert!(TriangleMesh::new(vec![Point3::origin()], vec![[0, 0, 1]]).is_err());
+    }
+}
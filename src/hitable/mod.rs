@@ -1,9 +1,17 @@
+pub use self::aabb::Aabb;
 pub use self::hitable::Hitable;
 pub use self::hitablelist::HitableList;
 pub use self::hitrecord::HitRecord;
+pub use self::mesh::{Mesh, TriangleMesh};
 pub use self::sphere::Sphere;
+pub use self::transformed::TransformedHitable;
 
+mod aabb;
 mod hitable;
 mod hitablelist;
 mod hitrecord;
-mod sphere;
\ No newline at end of file
+mod mesh;
+mod ply;
+mod sphere;
+mod stl;
+mod transformed;
\ No newline at end of file
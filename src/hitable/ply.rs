@@ -0,0 +1,407 @@
+use crate::srgb_to_linear;
+use crate::Color;
+use crate::Float;
+use crate::Normal3;
+use crate::Point3;
+use crate::TriangleMesh;
+
+use super::mesh::invalid_data;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+impl TriangleMesh {
+    /// Loads a PLY mesh, either ASCII or binary (in either byte order)
+    /// Vertex normals, texture coordinates (u/v or s/t) and colors (red/green/blue) are loaded when the file has them,
+    /// faces with more than three corners are split into triangles
+    pub fn from_ply<P: AsRef<Path>>(path: P) -> io::Result<TriangleMesh> {
+        let bytes = fs::read(path)?;
+        TriangleMesh::parse_ply(&bytes)
+    }
+
+    pub fn parse_ply(bytes: &[u8]) -> io::Result<TriangleMesh> {
+        let (header, data) = Header::parse(bytes)?;
+        let mut values = Values {
+            bytes: data,
+            cursor: 0,
+            encoding: header.encoding,
+        };
+
+        let mut vertices = None;
+        let mut triangles = None;
+        for element in header.elements.iter() {
+            match element.name.as_str() {
+                "vertex" => vertices = Some(read_vertices(element, &mut values)?),
+                "face" => triangles = Some(read_faces(element, &mut values)?),
+                _ => {
+                    for index in 0..element.count {
+                        for property in element.properties.iter() {
+                            values.read_property(property).map_err(|error| element_error(element, index, error))?;
+                        }
+                    }
+                },
+            }
+        }
+
+        let vertices = vertices.ok_or_else(|| invalid_data("PLY file has no vertex element".to_string()))?;
+        let triangles = match triangles {
+            Some(triangles) if !triangles.is_empty() => triangles,
+            // point clouds have nothing to hit
+            _ => return Err(invalid_data("PLY file has no faces".to_string())),
+        };
+
+        let mut mesh = TriangleMesh::new(vertices.positions, triangles)?;
+        if let Some(normals) = vertices.normals {
+            mesh = mesh.with_normals(normals)?;
+        }
+        if let Some(uvs) = vertices.uvs {
+            mesh = mesh.with_uvs(uvs)?;
+        }
+        if let Some(colors) = vertices.colors {
+            mesh = mesh.with_colors(colors)?;
+        }
+        return Ok(mesh);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Scalar> {
+        match name {
+            "char" | "int8" => Ok(Scalar::Int8),
+            "uchar" | "uint8" => Ok(Scalar::UInt8),
+            "short" | "int16" => Ok(Scalar::Int16),
+            "ushort" | "uint16" => Ok(Scalar::UInt16),
+            "int" | "int32" => Ok(Scalar::Int32),
+            "uint" | "uint32" => Ok(Scalar::UInt32),
+            "float" | "float32" => Ok(Scalar::Float32),
+            "double" | "float64" => Ok(Scalar::Float64),
+            _ => Err(invalid_data(format!("unknown PLY property type {}", name))),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Scalar::Int8 | Scalar::UInt8 => 1,
+            Scalar::Int16 | Scalar::UInt16 => 2,
+            Scalar::Int32 | Scalar::UInt32 | Scalar::Float32 => 4,
+            Scalar::Float64 => 8,
+        }
+    }
+
+    /// Colors stored as integers go from 0 to the type's maximum, floating point colors from 0 to 1
+    fn color_scale(&self) -> f64 {
+        match self {
+            Scalar::UInt8 | Scalar::Int8 => 255.0,
+            Scalar::UInt16 | Scalar::Int16 => 65535.0,
+            Scalar::UInt32 | Scalar::Int32 => u32::MAX as f64,
+            Scalar::Float32 | Scalar::Float64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PropertyKind {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+}
+
+impl Header {
+    /// The header, and the data that follows it
+    fn parse(bytes: &[u8]) -> io::Result<(Header, &[u8])> {
+        let mut encoding = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let mut cursor = 0;
+        let mut line_number = 0;
+        loop {
+            let line_end = bytes[cursor..].iter().position(|&byte| byte == b'\n')
+                .ok_or_else(|| invalid_data("PLY header ended before end_header".to_string()))?;
+            let line = std::str::from_utf8(&bytes[cursor..cursor + line_end])
+                .map_err(|_| invalid_data(format!("PLY header line {} isn't text", line_number + 1)))?;
+            cursor += line_end + 1;
+            line_number += 1;
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if line_number == 1 {
+                if words != ["ply"] {
+                    return Err(invalid_data("not a PLY file, it doesn't start with \"ply\"".to_string()));
+                }
+                continue;
+            }
+
+            let malformed = || invalid_data(format!("PLY header line {} is malformed: {}", line_number, line.trim()));
+            match words.as_slice() {
+                ["format", format, "1.0"] => encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    _ => return Err(invalid_data(format!("unknown PLY format {}", format))),
+                }),
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| malformed())?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count, item, name] => {
+                    let property = Property { name: name.to_string(), kind: PropertyKind::List { count: Scalar::parse(count)?, item: Scalar::parse(item)? } };
+                    elements.last_mut().ok_or_else(malformed)?.properties.push(property);
+                },
+                ["property", kind, name] => {
+                    let property = Property { name: name.to_string(), kind: PropertyKind::Scalar(Scalar::parse(kind)?) };
+                    elements.last_mut().ok_or_else(malformed)?.properties.push(property);
+                },
+                ["end_header"] => break,
+                ["comment", ..] | ["obj_info", ..] | [] => {},
+                _ => return Err(malformed()),
+            }
+        }
+
+        let encoding = encoding.ok_or_else(|| invalid_data("PLY header has no format line".to_string()))?;
+        return Ok((Header { encoding: encoding, elements: elements }, &bytes[cursor..]));
+    }
+}
+
+/// Reads the values of the elements one by one, from text or binary
+struct Values<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+    encoding: Encoding,
+}
+
+impl<'a> Values<'a> {
+    fn read(&mut self, scalar: Scalar) -> io::Result<f64> {
+        if self.encoding == Encoding::Ascii {
+            while self.cursor < self.bytes.len() && self.bytes[self.cursor].is_ascii_whitespace() {
+                self.cursor += 1;
+            }
+            let start = self.cursor;
+            while self.cursor < self.bytes.len() && !self.bytes[self.cursor].is_ascii_whitespace() {
+                self.cursor += 1;
+            }
+            if start == self.cursor {
+                return Err(invalid_data("the file ended unexpectedly".to_string()));
+            }
+
+            let token = String::from_utf8_lossy(&self.bytes[start..self.cursor]);
+            return token.parse().map_err(|_| invalid_data(format!("{} is not a number", token)));
+        }
+
+        let size = scalar.size();
+        if self.cursor + size > self.bytes.len() {
+            return Err(invalid_data("the file ended unexpectedly".to_string()));
+        }
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.cursor..self.cursor + size]);
+        self.cursor += size;
+        if self.encoding == Encoding::BinaryBigEndian {
+            raw[..size].reverse();
+        }
+
+        let value = match scalar {
+            Scalar::Int8 => raw[0] as i8 as f64,
+            Scalar::UInt8 => raw[0] as f64,
+            Scalar::Int16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::UInt16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::Int32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::UInt32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::Float32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::Float64 => f64::from_le_bytes(raw),
+        };
+        return Ok(value);
+    }
+
+    /// All the values of a property, one for scalars and any number for lists
+    fn read_property(&mut self, property: &Property) -> io::Result<Vec<f64>> {
+        match property.kind {
+            PropertyKind::Scalar(scalar) => Ok(vec![self.read(scalar)?]),
+            PropertyKind::List { count, item } => {
+                let count = self.read(count)?;
+                if count < 0.0 || count.fract() != 0.0 {
+                    return Err(invalid_data(format!("{} has a list length of {}", property.name, count)));
+                }
+                (0..count as usize).map(|_| self.read(item)).collect()
+            },
+        }
+    }
+}
+
+struct Vertices {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Normal3>>,
+    uvs: Option<Vec<(Float, Float)>>,
+    colors: Option<Vec<Color>>,
+}
+
+fn read_vertices(element: &Element, values: &mut Values) -> io::Result<Vertices> {
+    let find = |names: &[&str]| element.properties.iter().position(|property| names.contains(&property.name.as_str()) && matches!(property.kind, PropertyKind::Scalar(_)));
+    let find_all = |names: &[&[&str]]| names.iter().map(|names| find(names)).collect::<Option<Vec<usize>>>();
+    let position = find_all(&[&["x"], &["y"], &["z"]])
+        .ok_or_else(|| invalid_data("PLY vertices don't have x, y and z".to_string()))?;
+    let normal = find_all(&[&["nx"], &["ny"], &["nz"]]);
+    let uv = find_all(&[&["u", "s", "texture_u", "texture_s"], &["v", "t", "texture_v", "texture_t"]]);
+    let color = find_all(&[&["red", "diffuse_red", "r"], &["green", "diffuse_green", "g"], &["blue", "diffuse_blue", "b"]]);
+    let color_scales: Vec<f64> = color.iter().flatten().map(|&index| match element.properties[index].kind {
+        PropertyKind::Scalar(scalar) => scalar.color_scale(),
+        PropertyKind::List { .. } => unreachable!("only scalar properties are found"),
+    }).collect();
+
+    // the count in the header can't be trusted until the data is there, so don't allocate for it up front
+    let capacity = element.count.min(values.bytes.len());
+    let mut vertices = Vertices {
+        positions: Vec::with_capacity(capacity),
+        normals: normal.as_ref().map(|_| Vec::with_capacity(capacity)),
+        uvs: uv.as_ref().map(|_| Vec::with_capacity(capacity)),
+        colors: color.as_ref().map(|_| Vec::with_capacity(capacity)),
+    };
+
+    let mut vertex = vec![0.0; element.properties.len()];
+    for index in 0..element.count {
+        for (slot, property) in element.properties.iter().enumerate() {
+            let property_values = values.read_property(property).map_err(|error| element_error(element, index, error))?;
+            vertex[slot] = property_values.first().copied().unwrap_or(0.0);
+        }
+
+        let at = |slots: &Vec<usize>, axis: usize| vertex[slots[axis]] as Float;
+        vertices.positions.push(Point3::new(at(&position, 0), at(&position, 1), at(&position, 2)));
+        if let (Some(normals), Some(slots)) = (&mut vertices.normals, &normal) {
+            normals.push(Normal3::new(at(slots, 0), at(slots, 1), at(slots, 2)));
+        }
+        if let (Some(uvs), Some(slots)) = (&mut vertices.uvs, &uv) {
+            uvs.push((at(slots, 0), at(slots, 1)));
+        }
+        if let (Some(colors), Some(slots)) = (&mut vertices.colors, &color) {
+            // colors in files are display colors, the renderer works in linear
+            let channel = |axis: usize| srgb_to_linear((vertex[slots[axis]] / color_scales[axis]) as f32);
+            colors.push(Color::new(channel(0), channel(1), channel(2)));
+        }
+    }
+
+    return Ok(vertices);
+}
+
+/// Faces as triangles, polygons are split into a fan around their first corner
+fn read_faces(element: &Element, values: &mut Values) -> io::Result<Vec<[u32; 3]>> {
+    let indices = element.properties.iter()
+        .position(|property| (property.name == "vertex_indices" || property.name == "vertex_index") && matches!(property.kind, PropertyKind::List { .. }))
+        .ok_or_else(|| invalid_data("PLY faces don't have a vertex_indices list".to_string()))?;
+
+    let mut triangles = Vec::with_capacity(element.count.min(values.bytes.len()));
+    for index in 0..element.count {
+        for (slot, property) in element.properties.iter().enumerate() {
+            let property_values = values.read_property(property).map_err(|error| element_error(element, index, error))?;
+            if slot != indices {
+                continue;
+            }
+
+            if property_values.len() < 3 {
+                return Err(element_error(element, index, invalid_data(format!("it has only {} corners", property_values.len()))));
+            }
+            let mut corners = Vec::with_capacity(property_values.len());
+            for value in property_values {
+                if value < 0.0 || value.fract() != 0.0 || value > u32::MAX as f64 {
+                    return Err(element_error(element, index, invalid_data(format!("{} isn't a vertex index", value))));
+                }
+                corners.push(value as u32);
+            }
+            for corner in 1..corners.len() - 1 {
+                triangles.push([corners[0], corners[corner], corners[corner + 1]]);
+            }
+        }
+    }
+
+    return Ok(triangles);
+}
+
+/// Says which element the error happened in, "PLY vertex 12 of 100: the file ended unexpectedly"
+fn element_error(element: &Element, index: usize, error: io::Error) -> io::Error {
+    invalid_data(format!("PLY {} {} of {}: {}", element.name, index, element.count, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE_HEADER: &str = "ply\nformat {}\ncomment a unit square with colored corners\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    fn binary_square(little_endian: bool) -> Vec<u8> {
+        let format = if little_endian { "binary_little_endian 1.0" } else { "binary_big_endian 1.0" };
+        let mut bytes = SQUARE_HEADER.replace("{}", format).into_bytes();
+        let corners = [(0.0f32, 0.0f32, 255u8), (1.0, 0.0, 0), (1.0, 1.0, 255), (0.0, 1.0, 0)];
+        for (x, y, red) in corners.iter() {
+            for value in [*x, *y, 0.0].iter() {
+                bytes.extend_from_slice(&if little_endian { value.to_le_bytes() } else { value.to_be_bytes() });
+            }
+            bytes.extend_from_slice(&[*red, 0, 0]);
+        }
+        bytes.push(4);
+        for index in 0..4i32 {
+            bytes.extend_from_slice(&if little_endian { index.to_le_bytes() } else { index.to_be_bytes() });
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn ply_loads_ascii_and_binary_with_colors() {
+        let ascii = SQUARE_HEADER.replace("{}", "ascii 1.0") + "0 0 0 255 0 0\n1 0 0 0 0 0\n1 1 0 255 0 0\n0 1 0 0 0 0\n4 0 1 2 3\n";
+        let meshes = [ascii.into_bytes(), binary_square(true), binary_square(false)];
+        for bytes in meshes.iter() {
+            let mesh = TriangleMesh::parse_ply(bytes).unwrap();
+            assert_eq!(&[[0, 1, 2], [0, 2, 3]], mesh.indices());
+            assert_eq!(Point3::new(1.0, 1.0, 0.0), mesh.positions()[2]);
+            let colors = mesh.colors().unwrap();
+            assert_eq!((Color::new(1.0, 0.0, 0.0), Color::black()), (colors[0].clone(), colors[1].clone()));
+        }
+    }
+
+    #[test]
+    fn ply_reports_truncated_and_malformed_files() {
+        let error = |bytes: &[u8]| TriangleMesh::parse_ply(bytes).unwrap_err().to_string();
+
+        let square = binary_square(true);
+        assert_eq!("PLY face 0 of 1: the file ended unexpectedly", error(&square[..square.len() - 2]));
+        assert!(error(&square[..40]).contains("before end_header"));
+        let ascii = SQUARE_HEADER.replace("{}", "ascii 1.0") + "0 0 0 255 0 0\n1 0 zero 0 0 0\n";
+        assert_eq!("PLY vertex 1 of 4: zero is not a number", error(ascii.as_bytes()));
+        let out_of_range = SQUARE_HEADER.replace("{}", "ascii 1.0") + "0 0 0 0 0 0\n1 0 0 0 0 0\n1 1 0 0 0 0\n0 1 0 0 0 0\n3 0 1 7\n";
+        assert!(error(out_of_range.as_bytes()).contains("refers to a vertex past the 4 vertices"));
+        assert!(error(b"solid cube\n").contains("not a PLY file"));
+        assert!(error(SQUARE_HEADER.replace("{}", "binary 2.0").as_bytes()).contains("malformed: format binary 2.0"));
+    }
+}
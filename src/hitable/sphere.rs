@@ -1,17 +1,27 @@
+use crate::float::{self, consts};
+use crate::Float;
+#[cfg(not(feature = "f64"))]
+use crate::simd::{F32x4, RayPacket4, Vector3x4};
+use crate::Aabb;
 use crate::Hitable;
 use crate::HitRecord;
+use crate::Normal3;
+use crate::Point3;
 use crate::Ray;
 use crate::Vector3;
 use crate::material::Material;
 
+use std::sync::Arc;
+
 pub struct Sphere {
-    center: Vector3,
-    radius: f32,
-    material: Box<dyn Material>
+    center: Point3,
+    radius: Float,
+    /// Shared, any number of spheres can use the same material
+    material: Arc<dyn Material>
 }
 
 impl Sphere {
-    pub fn new(center: Vector3, radius: f32, material: Box<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: Float, material: Arc<dyn Material>) -> Self {
         Sphere {
             center: center,
             radius: radius,
@@ -19,17 +29,74 @@ impl Sphere {
         }
     }
 
-    pub fn center(&self) -> &Vector3 {
+    pub fn center(&self) -> &Point3 {
         return &self.center;
     }
 
-    pub fn radius(&self) -> f32 {
+    pub fn radius(&self) -> Float {
         return self.radius;
     }
+
+    /// Builds the HitRecord for the point where the ray is at time_at_hit
+    fn hit_record_at(&self, ray: &Ray, time_at_hit: Float) -> HitRecord<'_> {
+        // The point on the ray carries the rounding error of the whole intersection, projecting it back onto
+        // the sphere leaves only the few operations of the projection itself (Physically Based Rendering, section 3.9.4)
+        let offset = ray.point_at_time(time_at_hit) - self.center();
+        let offset = &offset * (self.radius().abs() / offset.magnitude());
+        let point_at_hit = self.center() + &offset;
+        let point_error = Vector3::new(
+            float::gamma(5) * offset.x().abs() + float::gamma(1) * point_at_hit.x().abs(),
+            float::gamma(5) * offset.y().abs() + float::gamma(1) * point_at_hit.y().abs(),
+            float::gamma(5) * offset.z().abs() + float::gamma(1) * point_at_hit.z().abs());
+
+        let normal = Normal3::from_vector(&(&offset / self.radius()));
+        let (uv, tangent, bitangent) = self.surface_coordinates(&point_at_hit);
+        let mut hit_record = HitRecord::new(time_at_hit, point_at_hit, normal, uv, tangent, bitangent, &*self.material);
+        hit_record.point_error = point_error;
+        return hit_record;
+    }
+
+    /// Maps a point on the sphere to (u, v) and the analytic derivatives dP/du and dP/dv
+    ///
+    /// theta is the angle down from the "north pole" (+Y), phi is the angle around the Y axis starting at -X
+    /// u = phi / 2PI and v = theta / PI, so the point is
+    /// P = center + radius * (-sin(theta) * cos(phi), -cos(theta), sin(theta) * sin(phi))
+    /// Differentiating that gives the tangents, which always satisfy cross(dP/du, dP/dv) pointing outward
+    /// Hollow spheres (negative radius) have their normal pointing inward, so their bitangent is flipped to match it,
+    /// otherwise normal maps on them would come out mirrored
+    fn surface_coordinates(&self, point: &Point3) -> ((Float, Float), Vector3, Vector3) {
+        let (uv, tangent, bitangent) = self.outward_surface_coordinates(point);
+        if self.radius() < 0.0 {
+            return (uv, tangent, -bitangent);
+        }
+        return (uv, tangent, bitangent);
+    }
+
+    fn outward_surface_coordinates(&self, point: &Point3) -> ((Float, Float), Vector3, Vector3) {
+        let direction = &(point - self.center()) / self.radius().abs();
+        let (x, y, z) = (direction.x(), direction.y(), direction.z());
+        let theta = (-y).clamp(-1.0, 1.0).acos();
+        let phi = (-z).atan2(x) + consts::PI;
+        let uv = (phi / (2.0 * consts::PI), theta / consts::PI);
+
+        // sin(theta), which goes to 0 at the poles where the parameterization degenerates
+        let rho = (x * x + z * z).sqrt();
+        if rho < 1e-6 {
+            // any tangent in the surface plane will do at the poles
+            let tangent = Vector3::new(1.0, 0.0, 0.0);
+            let bitangent = direction.cross(&tangent);
+            return (uv, tangent, bitangent);
+        }
+
+        let radius = self.radius().abs();
+        let tangent = 2.0 * consts::PI * radius * Vector3::new(z, 0.0, -x);
+        let bitangent = consts::PI * radius * Vector3::new(-y * x / rho, rho, -y * z / rho);
+        return (uv, tangent, bitangent);
+    }
 }
 
 impl Hitable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         // The book was really hard to unpack for this part. I struggled unpacking the algebra
         // Basically, we're determining if the "Ray" along ALL values of T will hit a sphere at point "sphere_center"
 
@@ -52,65 +119,183 @@ impl Hitable for Sphere {
         // I forgot about the quadratic behavior, but reading up on wikipedia got me back up to speed: https://en.wikipedia.org/wiki/Quadratic_formula#Geometrical_significance
         // Essentially, the number of solutions to the formula are given by b^2 - 4ac
         let sphere_origin_vector = ray.origin() - self.center();
-        let a = Vector3::dot(&ray.direction(), &ray.direction());
+        let a = Vector3::dot(ray.direction(), ray.direction());
         let b = Vector3::dot(ray.direction(), &sphere_origin_vector);
         let c = Vector3::dot(&sphere_origin_vector, &sphere_origin_vector) - self.radius() * self.radius();
 
-        let discriminant = b*b - a * c;
+        // b*b - a*c subtracts two nearly equal, huge numbers when the sphere is big (like the ground) or far away,
+        // and most of the digits cancel out. The same discriminant written as a * (R^2 - |closest|^2), where closest is
+        // the vector from the center to the point of the ray closest to it, never does (Ray Tracing Gems, chapter 7)
+        let closest = &sphere_origin_vector - ray.direction() * (b / a);
+        let discriminant = a * (self.radius() * self.radius() - closest.magnitude_squared());
         // negative means no real solution
         // 0 means the ray does not hit Sphere at center vector with radius
         // more than 0 means that many hits (frankly, one or two)
-        if discriminant < 0f32 {
+        if discriminant < 0.0 {
             return None;
         }
 
-        let time_at_hit = (-b - discriminant.sqrt()) / a;
-        if t_min < time_at_hit && time_at_hit < t_max {
-            let point_at_hit = ray.point_at_time(time_at_hit);
-            let normal = &(&point_at_hit - self.center()) / self.radius();
-            let hr = HitRecord::new(time_at_hit, point_at_hit, normal, Box::new(&*self.material));
-            return Some(hr);
-        }
+        // -b +- sqrt(discriminant) cancels out for one of the two roots, so only the other one is computed that way
+        // and the first follows from the product of the roots, c / a
+        let q = -(b + discriminant.sqrt().copysign(b));
+        let (first, second) = (q / a, c / q);
 
-        let time_at_hit = (-b + discriminant.sqrt()) / a;
-        if t_min < time_at_hit && time_at_hit < t_max {
-            let point_at_hit = ray.point_at_time(time_at_hit);
-            let normal = &(&point_at_hit - self.center()) / self.radius();
-            let hr = HitRecord::new(time_at_hit, point_at_hit, normal, Box::new(&*self.material));
-            return Some(hr);
+        // Check the near hit first, then the far one
+        // A cut-out material can be transparent at the near hit, in which case the ray continues into the sphere
+        for time_at_hit in [first.min(second), first.max(second)] {
+            if t_min < time_at_hit && time_at_hit < t_max {
+                let hit_record = self.hit_record_at(ray, time_at_hit);
+                if !hit_record.material.is_transparent_at(&hit_record) {
+                    return Some(hit_record);
+                }
+            }
         }
 
         return None;
     }
+
+    /// The quadratic from hit() for four rays at once, lanes that miss the sphere entirely are done after the discriminant
+    /// The lanes are f32, so with the f64 feature packets fall back to hit() for every ray
+    #[cfg(not(feature = "f64"))]
+    fn hit_packet<'a>(&'a self, packet: &RayPacket4, t_min: Float, t_max: &mut [Float; 4], hits: &mut [Option<HitRecord<'a>>; 4]) {
+        let sphere_origin_vector = packet.origin.sub(&Vector3x4::splat(self.center().as_vector()));
+        let a = packet.direction.dot(&packet.direction);
+        let b = packet.direction.dot(&sphere_origin_vector);
+        let c = sphere_origin_vector.dot(&sphere_origin_vector) - F32x4::splat(self.radius() * self.radius());
+
+        let t_closest = b / a;
+        let closest = sphere_origin_vector.sub(&Vector3x4 {
+            x: packet.direction.x * t_closest,
+            y: packet.direction.y * t_closest,
+            z: packet.direction.z * t_closest,
+        });
+        let discriminant = a * (F32x4::splat(self.radius() * self.radius()) - closest.dot(&closest));
+        let misses = discriminant.lt(F32x4::splat(0.0));
+        if misses.bits() == 0b1111 {
+            return;
+        }
+
+        let root = discriminant.sqrt();
+        let q = F32x4::select(b.lt(F32x4::splat(0.0)), root - b, -(b + root));
+        let (first, second) = (q / a, c / q);
+        let near = first.min(second).to_array();
+        let far = first.max(second).to_array();
+        for lane in 0..4 {
+            if misses.lane(lane) {
+                continue;
+            }
+
+            // same as hit(): the near hit first, unless a cut-out material is transparent there
+            for time_at_hit in [near[lane], far[lane]] {
+                if t_min < time_at_hit && time_at_hit < t_max[lane] {
+                    let hit_record = self.hit_record_at(packet.rays[lane], time_at_hit);
+                    if !hit_record.material.is_transparent_at(&hit_record) {
+                        t_max[lane] = time_at_hit;
+                        hits[lane] = Some(hit_record);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let radius = self.radius().abs();
+        let extent = Vector3::new(radius, radius, radius);
+        Some(Aabb::new(self.center() - &extent, self.center() + &extent))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::simd::RayPacket4;
     use crate::DialetricMaterial;
     use super::*;
 
     #[test]
     fn hitable_collides() {
-        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
-        let sphere = Sphere::new(Vector3::new(5.0, 0.0, 0.0), 1.0, Box::new(DialetricMaterial::new(1.0)));
+        let ray = Ray::new(Point3::origin(), Vector3::new(1.0, 0.0, 0.0));
+        let sphere = Sphere::new(Point3::new(5.0, 0.0, 0.0), 1.0, Arc::new(DialetricMaterial::new(1.0)));
 
         let result = sphere.hit(&ray, 0.001, 10.0);
         
         assert!(result.is_some());
         let hit_record = result.unwrap();
-        assert_eq!(Vector3::new(4.0, 0.0, 0.0), hit_record.point);
+        assert_eq!(Point3::new(4.0, 0.0, 0.0), hit_record.point);
         assert_eq!(4.0, hit_record.hit_at);
-        assert_eq!(Vector3::new(-1.0, 0.0, 0.0), hit_record.normal);
+        assert_eq!(Normal3::new(-1.0, 0.0, 0.0), hit_record.normal);
         //assert_eq!(sphere.material, hit_record.material);
     }
 
     #[test]
     fn hitable_doesnotcollide() {
-        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
-        let sphere = Sphere::new(Vector3::new(5.0, 0.0, 0.0), 1.0, Box::new(DialetricMaterial::new(1.0)));
+        let ray = Ray::new(Point3::origin(), Vector3::new(-1.0, 0.0, 0.0));
+        let sphere = Sphere::new(Point3::new(5.0, 0.0, 0.0), 1.0, Arc::new(DialetricMaterial::new(1.0)));
 
         let result = sphere.hit(&ray, 0.001, 10.0);
         
         assert!(result.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hitable_tangents_follow_surface() {
+        // hollow spheres have their normal pointing inward, the tangents have to turn with it
+        let rays = [
+            Ray::new(Point3::new(0.0, 0.5, -5.0), Vector3::new(0.0, 0.0, 1.0)),
+            Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+        ];
+        for radius in [1.0, -1.0] {
+            let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), radius, Arc::new(DialetricMaterial::new(1.0)));
+            for ray in rays.iter() {
+                let hit_record = sphere.hit(ray, 0.001, 10.0).unwrap();
+
+                assert!(hit_record.normal.dot(&hit_record.tangent).abs() < 1e-5);
+                assert!(hit_record.normal.dot(&hit_record.bitangent).abs() < 1e-5);
+                assert!(hit_record.normal.dot(&hit_record.tangent.cross(&hit_record.bitangent)) > 0.0, "radius {}", radius);
+                assert!(0.0 <= hit_record.u && hit_record.u <= 1.0);
+                assert!(0.0 <= hit_record.v && hit_record.v <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn hitable_spawned_rays_never_hit_their_own_surface() {
+        // the ground of the random scene, seen from far away at grazing angles
+        let ground = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, Arc::new(DialetricMaterial::new(1.0)));
+        for i in 0..1000 {
+            let x = i as Float * 0.37 - 185.0;
+            let ray = Ray::new(Point3::new(x, 2.0 + (i % 7) as Float, 300.0), Vector3::new(-x * 0.001, -1.0, -3.0 - (i % 11) as Float));
+            let hit_record = match ground.hit(&ray, 0.0, Float::MAX) {
+                Some(hit_record) => hit_record,
+                None => continue,
+            };
+
+            // leaving the convex sphere outwards, even at a grazing angle, there is nothing left to hit
+            let angle = i as Float;
+            let direction = Vector3::unit_vector(&hit_record.tangent) * angle.cos() + Vector3::unit_vector(&hit_record.bitangent) * angle.sin()
+                + hit_record.normal.as_vector() * 0.001;
+            assert!(ground.hit(&hit_record.spawn_ray(direction), 0.0, Float::MAX).is_none(), "ray {} hit its own surface", i);
+        }
+    }
+
+    #[test]
+    fn hitable_packet_agrees_with_hit() {
+        let sphere = Sphere::new(Point3::new(5.0, 0.0, 0.0), 1.0, Arc::new(DialetricMaterial::new(1.0)));
+        let rays = [
+            Ray::new(Point3::origin(), Vector3::new(1.0, 0.0, 0.0)),
+            Ray::new(Point3::origin(), Vector3::new(-1.0, 0.0, 0.0)),
+            Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::new(0.3, 0.4, 0.5)),
+            Ray::new(Point3::new(0.0, 0.9, 0.0), Vector3::new(2.0, 0.0, 0.1)),
+        ];
+        let packet = RayPacket4::new([&rays[0], &rays[1], &rays[2], &rays[3]]);
+        let t_max = [10.0, 10.0, 10.0, 2.0];
+
+        let mut hits = [None, None, None, None];
+        sphere.hit_packet(&packet, 0.001, &mut t_max.clone(), &mut hits);
+
+        for (lane, ray) in rays.iter().enumerate() {
+            let expected = sphere.hit(ray, 0.001, t_max[lane]).map(|hit_record| hit_record.hit_at);
+            assert_eq!(expected, hits[lane].as_ref().map(|hit_record| hit_record.hit_at), "lane {}", lane);
+        }
+    }
+}
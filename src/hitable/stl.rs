@@ -0,0 +1,172 @@
+use crate::Float;
+use crate::Point3;
+use crate::TriangleMesh;
+
+use super::mesh::invalid_data;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Binary STL: an 80 byte header, the triangle count, then 50 bytes per triangle
+const BINARY_HEADER_SIZE: usize = 84;
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+impl TriangleMesh {
+    /// Loads an STL mesh, either ASCII or binary
+    /// STL triangles don't share vertices and have no normals, so the mesh is flat shaded
+    pub fn from_stl<P: AsRef<Path>>(path: P) -> io::Result<TriangleMesh> {
+        let bytes = fs::read(path)?;
+        TriangleMesh::parse_stl(&bytes)
+    }
+
+    pub fn parse_stl(bytes: &[u8]) -> io::Result<TriangleMesh> {
+        // binary files may start with "solid" too (some exporters also pad them past their triangles),
+        // so a file that isn't valid ASCII STL but holds all the triangles its header counts is read as binary
+        let positions = if bytes.trim_ascii_start().starts_with(b"solid") {
+            match parse_ascii(bytes) {
+                Ok(positions) => positions,
+                Err(_) if matches!(binary_size(bytes), Some(size) if size <= bytes.len()) => parse_binary(bytes)?,
+                Err(ascii_error) => {
+                    // the triangles don't fit, so this fails too
+                    let binary_error = parse_binary(bytes).unwrap_err();
+                    return Err(invalid_data(format!("STL file is neither ASCII ({}) nor binary ({})", ascii_error, binary_error)));
+                },
+            }
+        } else {
+            parse_binary(bytes)?
+        };
+
+        if positions.is_empty() {
+            return Err(invalid_data("STL file has no triangles".to_string()));
+        }
+        let triangles = (0..positions.len() as u32 / 3).map(|triangle| [3 * triangle, 3 * triangle + 1, 3 * triangle + 2]).collect();
+        return TriangleMesh::new(positions, triangles);
+    }
+}
+
+/// How long a binary STL file with the triangle count in its header should be
+fn binary_size(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(80..BINARY_HEADER_SIZE)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    return Some(BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE);
+}
+
+/// Three positions per triangle, the facet normals are left out (the winding says the same)
+fn parse_binary(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+    let size = binary_size(bytes).ok_or_else(|| invalid_data(format!("binary STL file is {} bytes, too short for its header", bytes.len())))?;
+    let count = (size - BINARY_HEADER_SIZE) / BINARY_TRIANGLE_SIZE;
+    if bytes.len() < size {
+        return Err(invalid_data(format!("binary STL file has {} triangles but ends after {}", count, (bytes.len() - BINARY_HEADER_SIZE) / BINARY_TRIANGLE_SIZE)));
+    }
+
+    let mut positions = Vec::with_capacity(count * 3);
+    for triangle in bytes[BINARY_HEADER_SIZE..size].chunks(BINARY_TRIANGLE_SIZE) {
+        // skips the normal, and the two attribute bytes at the end
+        for vertex in triangle[12..48].chunks(12) {
+            let coordinate = |axis: usize| f32::from_le_bytes([vertex[axis * 4], vertex[axis * 4 + 1], vertex[axis * 4 + 2], vertex[axis * 4 + 3]]) as Float;
+            positions.push(Point3::new(coordinate(0), coordinate(1), coordinate(2)));
+        }
+    }
+    return Ok(positions);
+}
+
+fn parse_ascii(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid_data("ASCII STL file isn't text".to_string()))?;
+    // the solid's name runs to the end of the first line
+    let mut lines = text.lines().enumerate();
+    lines.next();
+    let mut tokens = lines.flat_map(|(line, words)| words.split_whitespace().map(move |word| (line + 1, word)));
+
+    let mut positions = Vec::new();
+    loop {
+        match next(&mut tokens)? {
+            (_, "endsolid") => return Ok(positions),
+            (_, "facet") => {},
+            (line, other) => return Err(unexpected(line, "facet or endsolid", other)),
+        }
+
+        expect(&mut tokens, "normal")?;
+        for _ in 0..3 {
+            number(&mut tokens)?;
+        }
+        expect(&mut tokens, "outer")?;
+        expect(&mut tokens, "loop")?;
+        for _ in 0..3 {
+            expect(&mut tokens, "vertex")?;
+            positions.push(Point3::new(number(&mut tokens)?, number(&mut tokens)?, number(&mut tokens)?));
+        }
+        expect(&mut tokens, "endloop")?;
+        expect(&mut tokens, "endfacet")?;
+    }
+}
+
+fn next<'a>(tokens: &mut impl Iterator<Item = (usize, &'a str)>) -> io::Result<(usize, &'a str)> {
+    tokens.next().ok_or_else(|| invalid_data("ASCII STL file ended before endsolid".to_string()))
+}
+
+fn expect<'a>(tokens: &mut impl Iterator<Item = (usize, &'a str)>, keyword: &str) -> io::Result<()> {
+    match next(tokens)? {
+        (_, token) if token == keyword => Ok(()),
+        (line, token) => Err(unexpected(line, keyword, token)),
+    }
+}
+
+fn number<'a>(tokens: &mut impl Iterator<Item = (usize, &'a str)>) -> io::Result<Float> {
+    let (line, token) = next(tokens)?;
+    token.parse().map_err(|_| unexpected(line, "a number", token))
+}
+
+fn unexpected(line: usize, expected: &str, found: &str) -> io::Error {
+    invalid_data(format!("ASCII STL line {}: expected {}, found {}", line, expected, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = "solid triangle\n  facet normal 0 0 1\n    outer loop\n      vertex 0 0 0\n      vertex 1 0 0\n      vertex 0 1 0\n    endloop\n  endfacet\nendsolid triangle\n";
+
+    fn binary_triangle() -> Vec<u8> {
+        // binary files are allowed to start with "solid" as well
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        for value in [0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0, 0]);
+        return bytes;
+    }
+
+    #[test]
+    fn stl_loads_ascii_and_binary() {
+        for bytes in [TRIANGLE.as_bytes().to_vec(), binary_triangle()].iter() {
+            let mesh = TriangleMesh::parse_stl(bytes).unwrap();
+            assert_eq!(&[[0, 1, 2]], mesh.indices());
+            assert_eq!(&[Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)], mesh.positions());
+        }
+    }
+
+    #[test]
+    fn stl_reads_padded_binary_files_starting_with_solid() {
+        let mut bytes = binary_triangle();
+        bytes.extend_from_slice(&[0; 16]);
+        let mesh = TriangleMesh::parse_stl(&bytes).unwrap();
+        assert_eq!(&[Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)], mesh.positions());
+    }
+
+    #[test]
+    fn stl_reports_truncated_and_malformed_files() {
+        let error = |bytes: &[u8]| TriangleMesh::parse_stl(bytes).unwrap_err().to_string();
+
+        let mut binary = binary_triangle();
+        binary[0] = b'x';
+        assert_eq!("binary STL file has 1 triangles but ends after 0", error(&binary[..100]));
+        assert!(error(&binary[..50]).contains("too short for its header"));
+        let truncated = error(TRIANGLE.split("endloop").next().unwrap().as_bytes());
+        assert!(truncated.starts_with("STL file is neither ASCII (ASCII STL file ended before endsolid) nor binary ("), "{}", truncated);
+        assert!(error(TRIANGLE.replace("1 0 0", "1,0 0").as_bytes()).contains("ASCII STL line 5: expected a number, found 1,0"));
+        assert!(error(b"solid empty\nendsolid empty\n").contains("no triangles"));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::float;
+use crate::Float;
+use crate::Aabb;
+use crate::Hitable;
+use crate::HitRecord;
+use crate::Point3;
+use crate::Ray;
+use crate::Transform;
+use crate::Vector3;
+
+/// A hitable placed in the world by a transform, the hitable itself stays in its own (object) space
+/// Rays go into object space by the inverse transform and hits come back out, so a sphere can become an ellipsoid
+///
+/// The ray direction isn't renormalized in object space, which keeps the hit times the same in both spaces
+pub struct TransformedHitable {
+    hitable: Box<dyn Hitable>,
+    /// Object to world
+    transform: Transform,
+    world_to_object: Transform,
+}
+
+impl TransformedHitable {
+    pub fn new(hitable: Box<dyn Hitable>, transform: Transform) -> Self {
+        TransformedHitable {
+            hitable: hitable,
+            world_to_object: transform.inverse(),
+            transform: transform,
+        }
+    }
+}
+
+impl Hitable for TransformedHitable {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let object_ray = Ray::new(self.world_to_object.point(ray.origin()), self.world_to_object.vector(ray.direction()));
+        let mut hit_record = self.hitable.hit(&object_ray, t_min, t_max)?;
+
+        // the error grows by the rounding of the transform, and by how much the transform stretches it (PBRT section 3.9.3)
+        let matrix = self.transform.matrix();
+        let (point, error) = (&hit_record.point, &hit_record.point_error);
+        let transformed_error = |row: usize| {
+            let stretched = matrix[row][0].abs() * error.x() + matrix[row][1].abs() * error.y() + matrix[row][2].abs() * error.z();
+            let magnitude = (matrix[row][0] * point.x()).abs() + (matrix[row][1] * point.y()).abs() + (matrix[row][2] * point.z()).abs() + matrix[row][3].abs();
+            (float::gamma(3) + 1.0) * stretched + float::gamma(3) * magnitude
+        };
+        hit_record.point_error = Vector3::new(transformed_error(0), transformed_error(1), transformed_error(2));
+        hit_record.point = self.transform.point(&hit_record.point);
+        hit_record.normal = self.transform.normal(&hit_record.normal);
+        hit_record.geometric_normal = self.transform.normal(&hit_record.geometric_normal);
+        hit_record.tangent = self.transform.vector(&hit_record.tangent);
+        hit_record.bitangent = self.transform.vector(&hit_record.bitangent);
+        return Some(hit_record);
+    }
+
+    /// The object space box, with every corner transformed
+    fn bounding_box(&self) -> Option<Aabb> {
+        let object_box = self.hitable.bounding_box()?;
+        let (min, max) = (object_box.min(), object_box.max());
+        let mut corners = (0..8).map(|corner| {
+            let point = self.transform.point(&Point3::new(
+                if corner & 1 == 0 { min.x() } else { max.x() },
+                if corner & 2 == 0 { min.y() } else { max.y() },
+                if corner & 4 == 0 { min.z() } else { max.z() }));
+            Aabb::new(point.clone(), point)
+        });
+        let first = corners.next()?;
+        return Some(corners.fold(first, |union, corner| union.union(&corner)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::LambertarianMaterial;
+    use crate::Sphere;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn transformed_sphere_is_an_ellipsoid() {
+        let sphere = Sphere::new(Point3::origin(), 1.0, Arc::new(LambertarianMaterial::new(Color::gray(0.5))));
+        let transform = Transform::scaling(2.0, 1.0, 1.0).then(&Transform::translation(&Vector3::new(0.0, 0.0, -5.0)));
+        let ellipsoid = TransformedHitable::new(Box::new(sphere), transform);
+
+        // stretched to 2 along X, so a ray along X hits at x = 2 with the normal along X
+        let ray = Ray::new(Point3::new(10.0, 0.0, -5.0), Vector3::new(-1.0, 0.0, 0.0));
+        let hit_record = ellipsoid.hit(&ray, 0.0, Float::MAX).unwrap();
+        assert!((hit_record.hit_at - 8.0).abs() < 1e-4);
+        assert!((hit_record.point.x() - 2.0).abs() < 1e-4);
+        assert!((hit_record.normal.x() - 1.0).abs() < 1e-4);
+
+        // off axis the normal isn't the direction from the center, the inverse transpose tilts it
+        let ray = Ray::new(Point3::new(1.0, 10.0, -5.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit_record = ellipsoid.hit(&ray, 0.0, Float::MAX).unwrap();
+        let radial = Vector3::unit_vector(&(&hit_record.point - Point3::new(0.0, 0.0, -5.0)));
+        assert!(hit_record.normal.dot(&radial) < 0.99);
+        assert!(hit_record.normal.x() > 0.0 && hit_record.normal.y() > 0.0);
+
+        let bounding_box = ellipsoid.bounding_box().unwrap();
+        assert!((bounding_box.min().x() + 2.0).abs() < 1e-4 && (bounding_box.max().z() + 4.0).abs() < 1e-4);
+    }
+}
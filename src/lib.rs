@@ -0,0 +1,34 @@
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::module_inception)]
+// Float is f32 unless the f64 feature is on, so a cast between Float and f32 is only needed in one of the two builds
+#![allow(clippy::unnecessary_cast)]
+
+pub mod camera;
+pub mod film;
+pub mod float;
+pub mod hitable;
+pub mod light;
+pub mod material;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod sampler;
+pub mod sampling;
+pub mod scene;
+pub mod simd;
+pub mod ray;
+pub mod render;
+pub mod texture;
+pub mod vector;
+
+extern crate rand;
+
+pub use camera::*;
+pub use film::*;
+pub use float::Float;
+pub use hitable::*;
+pub use light::{LightSample, PunctualLight, SpotCone};
+pub use material::*;
+pub use ray::Ray;
+pub use render::{AdaptiveSampling, ProgressiveOutput, RenderSettings};
+pub use sampler::{Sampler, SamplerKind};
+pub use texture::*;
+pub use vector::*;
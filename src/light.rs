@@ -0,0 +1,102 @@
+use crate::Color;
+use crate::Float;
+use crate::Point3;
+use crate::Vector3;
+
+/// A light too small to ever be hit by a path: a point, a spot or the sun
+/// Instead of waiting for a bounce to find them, the renderer asks every light for its light at every surface
+/// it hits, and checks with a shadow ray whether anything is in the way (next event estimation, see render::trace_path)
+#[derive(Debug, Clone, PartialEq)]
+pub enum PunctualLight {
+    /// Intensity is the light given off in every direction, it falls off with the square of the distance
+    Point { position: Point3, intensity: Color },
+    /// A point light that only lights up a cone
+    Spot { position: Point3, intensity: Color, cone: SpotCone },
+    /// Infinitely far away, so it lights everything from the same direction with the same irradiance
+    Directional { direction: Vector3, irradiance: Color },
+}
+
+/// Full brightness within the inner cone, fading out smoothly to nothing at the outer cone
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotCone {
+    /// Unit length, where the spot points
+    pub axis: Vector3,
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+}
+
+impl SpotCone {
+    /// How much of the light goes out in direction (unit length), smoothstepped between the cones
+    pub fn falloff(&self, direction: &Vector3) -> f32 {
+        let cosine = Vector3::dot(direction, &self.axis) as f32;
+        let t = ((cosine - self.cos_outer) / (self.cos_inner - self.cos_outer).max(1e-6)).clamp(0.0, 1.0);
+        return t * t * (3.0 - 2.0 * t);
+    }
+}
+
+/// The light arriving at a point from one light
+pub struct LightSample {
+    /// Unit length, from the point toward the light
+    pub direction: Vector3,
+    /// How far away the light is, shadow rays stop there (Float::MAX for directional lights)
+    pub distance: Float,
+    /// Light arriving on a surface facing the light, before the cosine
+    pub irradiance: Color,
+}
+
+impl PunctualLight {
+    /// None when the point gets no light at all, outside a spot's cone for example
+    pub fn sample(&self, point: &Point3) -> Option<LightSample> {
+        let (position, intensity, cone) = match self {
+            PunctualLight::Point { position, intensity } => (position, intensity, None),
+            PunctualLight::Spot { position, intensity, cone } => (position, intensity, Some(cone)),
+            PunctualLight::Directional { direction, irradiance } => {
+                return Some(LightSample {
+                    direction: -Vector3::unit_vector(direction),
+                    distance: Float::MAX,
+                    irradiance: irradiance.clone(),
+                });
+            },
+        };
+
+        let offset = position - point;
+        let distance_squared = offset.magnitude_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+        let direction = &offset / distance;
+        let falloff = cone.map_or(1.0, |cone| cone.falloff(&-&direction));
+        if falloff == 0.0 {
+            return None;
+        }
+
+        return Some(LightSample {
+            direction: direction,
+            distance: distance,
+            irradiance: intensity * (falloff / distance_squared as f32),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_falls_off_with_distance_and_outside_the_cone() {
+        let point = PunctualLight::Point { position: Point3::new(0.0, 2.0, 0.0), intensity: Color::gray(8.0) };
+        let sample = point.sample(&Point3::origin()).unwrap();
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), sample.direction);
+        assert!((sample.distance - 2.0).abs() < 1e-6 && (sample.irradiance.r() - 2.0).abs() < 1e-6);
+
+        let cone = SpotCone { axis: Vector3::new(0.0, -1.0, 0.0), cos_inner: 0.9, cos_outer: 0.8 };
+        let spot = PunctualLight::Spot { position: Point3::new(0.0, 2.0, 0.0), intensity: Color::gray(8.0), cone: cone };
+        assert!((spot.sample(&Point3::origin()).unwrap().irradiance.r() - 2.0).abs() < 1e-6);
+        assert!(spot.sample(&Point3::new(2.0, 0.0, 0.0)).is_none());
+
+        let sun = PunctualLight::Directional { direction: Vector3::new(0.0, -2.0, 0.0), irradiance: Color::gray(3.0) };
+        assert_eq!(Vector3::new(0.0, 1.0, 0.0), sun.sample(&Point3::origin()).unwrap().direction);
+    }
+}
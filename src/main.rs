@@ -1,91 +1,293 @@
-mod camera;
-mod hitable;
-mod material;
-mod scene;
-mod ray;
-mod vector;
-
-extern crate rand;
-
-use camera::Camera;
-use hitable::*;
-use material::*;
-use ray::Ray;
-use vector::Vector3;
-
-use rand::prelude::*;
-
-/// Determines the Color that this Ray should have in the world
-/// First we determine the nearest object the ray is going to hit in the world
-/// If it hit, and we haven't reached the max hit-depth, re-cast the ray from the hitable's geometric material
-/// If it hit, and we have reached the max hit-depth, the "base-color" is solid black (0,0,0)
-/// If it did not hit, then the Ray has reflected into the background
-/// 
-/// current_depth is the number of times this Ray has bounced off of something
-fn color(ray: &Ray, world: &HitableList, current_depth: u32, max_depth: u32) -> Vector3 {
-    // 0.001 to correct for rays bouncing off at minimal floats (0.00000000001)
-    if let Some(hit_record) = world.hit(&ray, 0.001, std::f32::MAX) {
-        if current_depth < max_depth {
-            if let Some(scatter_material) = hit_record.material.scatter(ray, &hit_record) {
-                return scatter_material.albedo * color(&scatter_material.ray, world, current_depth + 1, max_depth);
+#![allow(clippy::needless_return)]
+
+use raytracing::render;
+use raytracing::sampler::hashing::hash_bytes;
+use raytracing::scene;
+use raytracing::scene::SceneGraph;
+use raytracing::{AdaptiveSampling, ApertureMask, ApertureShape, Denoiser, Film, Filter, ImageTexture, PhysicalCamera, Point3, PostProcess, ProgressiveOutput, Projection, RenderSettings, SamplerKind, ToneMapper, TriangleMesh, Vector3};
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: raytracing [--seed N] [--scene FILE] [--adaptive] [--heatmap PPM] [--progress PPM] [--resume STATE] [--save-state STATE] [--camera PROJECTION] [--aperture-mask PPM] [--exposure STOPS] [--tone-mapper CURVE] [--aovs DIR] [--denoise] [--preview] > image.ppm
+       raytracing --merge STATE STATE... [--save-state STATE] > image.ppm
+
+  --seed N            sampler seed, independent renders to merge later need different seeds
+  --scene FILE        render a glTF scene (.gltf or .glb, needs the gltf feature) through its first camera,
+                      or a single PLY or STL model, instead of the random scene
+  --adaptive          take 16 to 256 samples per pixel depending on how noisy it is, instead of 100 everywhere
+  --heatmap PPM       write an image of how many samples each pixel took to PPM (needs --adaptive)
+  --progress PPM      write the image rendered so far to PPM after every pass
+  --resume STATE      continue the render saved in STATE
+  --save-state STATE  save the accumulation state to STATE (periodically while rendering)
+  --merge STATE...    combine independent partial renders of the same scene into one image
+  --camera PROJECTION perspective (the default), physical (a 50mm f/2.8 lens with a six-bladed aperture),
+                      orthographic, fisheye or equirectangular (a 360 degree panorama)
+  --aperture-mask PPM the physical camera's aperture shape, brighter pixels let through more light
+  --exposure STOPS    brighten (or darken, when negative) the image by this many stops,
+                      on top of the physical camera's own exposure
+  --tone-mapper CURVE clamp (the default), reinhard, aces or agx, the last three roll off highlights
+                      instead of clipping them, for bright lights and emissive scenes
+  --aovs DIR          also write the AOVs (albedo, normal, depth, IDs, direct and indirect light) to DIR as PFM images
+  --denoise           denoise the final image, makes low sample counts usable for a quick look
+  --preview           show the render in a window while it refines (needs the preview feature)";
+
+/// Command line arguments
+#[derive(Default)]
+struct Arguments {
+    seed: u64,
+    scene_path: Option<PathBuf>,
+    adaptive: bool,
+    heatmap_path: Option<PathBuf>,
+    progress_path: Option<PathBuf>,
+    /// Overrides the scene camera's projection too, when given
+    projection: Option<Projection>,
+    resume_from: Option<PathBuf>,
+    state_path: Option<PathBuf>,
+    merge: Vec<PathBuf>,
+    aperture_mask: Option<PathBuf>,
+    exposure: f32,
+    /// Clamp when not given
+    tone_mapper: Option<ToneMapper>,
+    aov_directory: Option<PathBuf>,
+    denoise: bool,
+    preview: bool,
+}
+
+impl Arguments {
+    fn parse() -> Result<Arguments, String> {
+        let mut arguments = Arguments::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+            match arg.as_str() {
+                "--seed" => arguments.seed = value("--seed")?.parse().map_err(|_| "--seed needs a number".to_string())?,
+                "--scene" => arguments.scene_path = Some(PathBuf::from(value("--scene")?)),
+                "--adaptive" => arguments.adaptive = true,
+                "--heatmap" => arguments.heatmap_path = Some(PathBuf::from(value("--heatmap")?)),
+                "--progress" => arguments.progress_path = Some(PathBuf::from(value("--progress")?)),
+                "--resume" => arguments.resume_from = Some(PathBuf::from(value("--resume")?)),
+                "--save-state" => arguments.state_path = Some(PathBuf::from(value("--save-state")?)),
+                "--merge" => arguments.merge.push(PathBuf::from(value("--merge")?)),
+                "--camera" => arguments.projection = Some(match value("--camera")?.as_str() {
+                    "perspective" => Projection::Perspective,
+                    "physical" => Projection::Physical(PhysicalCamera {
+                        focal_length: 50.0,
+                        sensor_width: 36.0,
+                        f_number: 2.8,
+                        shutter_speed: 1.0 / 1000.0,
+                        iso: 100.0,
+                        focus_distance: 10.0,
+                        aperture_shape: ApertureShape::Polygon { blades: 6, rotation_deg: 0.0 },
+                    }),
+                    "orthographic" => Projection::Orthographic { view_height: 8.0 },
+                    "fisheye" => Projection::Fisheye { fov: 180.0 },
+                    "equirectangular" => Projection::Equirectangular,
+                    other => return Err(format!("unknown camera {}", other)),
+                }),
+                "--aperture-mask" => arguments.aperture_mask = Some(PathBuf::from(value("--aperture-mask")?)),
+                "--exposure" => arguments.exposure = value("--exposure")?.parse().map_err(|_| "--exposure needs a number of stops".to_string())?,
+                "--tone-mapper" => arguments.tone_mapper = Some(match value("--tone-mapper")?.as_str() {
+                    "clamp" => ToneMapper::Clamp,
+                    "reinhard" => ToneMapper::Reinhard,
+                    "aces" => ToneMapper::AcesFilmic,
+                    "agx" => ToneMapper::Agx,
+                    other => return Err(format!("unknown tone mapper {}", other)),
+                }),
+                "--aovs" => arguments.aov_directory = Some(PathBuf::from(value("--aovs")?)),
+                "--denoise" => arguments.denoise = true,
+                "--preview" => arguments.preview = true,
+                path if !arguments.merge.is_empty() && !path.starts_with("--") => arguments.merge.push(PathBuf::from(path)),
+                _ => return Err(format!("unexpected argument {}", arg)),
             }
         }
 
-        // passed the depth or we've attenuated the ray
-        return Vector3::new(0.0, 0.0, 0.0);
-    } else {
-        // did not hit an object, return the background
-        let ray_direction_unit = Vector3::unit_vector(&ray.direction());
-        let t = 0.5f32 * ray_direction_unit.y() + 1f32;
-        return &(&Vector3::new(1.0, 1.0, 1.0) * (1f32 - t)) + &(&Vector3::new(0.5, 0.7, 1.0) * t);
+        return Ok(arguments);
     }
 }
 
-fn raytracer(num_rows: u32, num_cols: u32, num_aa_samples: u32, max_ray_depth: u32, camera_aperture: f32, camera_vfov: f32, camera_focus_distance: f32) {
-    eprintln!("Generating scene");
-    let world = scene::random_scene();
-    eprintln!("Generated scene");
-    let aspect_ratio = num_rows as f32 / num_cols as f32;
-    let look_from = Vector3::new(13.0, 2.0, 3.0);
-    let look_at = Vector3::new(0.0, 0.0, 0.0);
-    let v_up = Vector3::new(0.0, 1.0, 0.0);
-
-    let camera = Camera::new(look_from, look_at, v_up, camera_vfov, aspect_ratio, camera_aperture, camera_focus_distance);
-
-    println!("P3\n{} {}\n255", num_rows, num_cols);
-    for y in (0..num_cols).rev() {
-        for x in 0..num_rows {
-            let mut aa_pixel = Vector3::new(0.0, 0.0, 0.0);
-            for _ in 0..num_aa_samples {
-                let u = (x as f32 + random::<f32>()) / num_rows as f32;
-                let v = (y as f32 + random::<f32>()) / num_cols as f32;
-                let ray = camera.get_ray(u, v);
-                let color = self::color(&ray, &world, 0, max_ray_depth);
-                aa_pixel += color;
-            }
+fn main() {
+    let mut arguments = Arguments::parse().unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, USAGE);
+        process::exit(2);
+    });
+
+    if arguments.heatmap_path.is_some() && !arguments.adaptive {
+        eprintln!("--heatmap needs --adaptive\n\n{}", USAGE);
+        process::exit(2);
+    }
+
+    if let Some(path) = &arguments.aperture_mask {
+        let physical_camera = match &mut arguments.projection {
+            Some(Projection::Physical(physical_camera)) => physical_camera,
+            _ => {
+                eprintln!("--aperture-mask needs --camera physical\n\n{}", USAGE);
+                process::exit(2);
+            },
+        };
+
+        let mask = ImageTexture::from_ppm(path).and_then(|image| ApertureMask::new(&image)).unwrap_or_else(|error| {
+            eprintln!("Failed to load the aperture mask {}: {}", path.display(), error);
+            process::exit(1);
+        });
+        physical_camera.aperture_shape = ApertureShape::Mask(mask);
+    }
+
+    // the physical camera brings its own exposure, --exposure adjusts it from there
+    let exposure = arguments.exposure + match &arguments.projection {
+        Some(Projection::Physical(physical_camera)) => physical_camera.exposure_stops(),
+        _ => 0.0,
+    };
+
+    let mut settings = RenderSettings {
+        width: 200,
+        height: 100,
+        num_aa_samples: if arguments.adaptive { 16 } else { 100 },
+        adaptive_sampling: if arguments.adaptive {
+            Some(AdaptiveSampling {
+                max_samples: 256,
+                error_threshold: 0.05,
+                heatmap_path: arguments.heatmap_path.clone(),
+            })
+        } else {
+            None
+        },
+        max_ray_depth: 50,
+        russian_roulette_depth: 3,
+        camera_look_from: Point3::new(13.0, 2.0, 3.0),
+        camera_look_at: Point3::origin(),
+        camera_v_up: Vector3::new(0.0, 1.0, 0.0),
+        camera_projection: arguments.projection.clone().unwrap_or_default(),
+        camera_aperture: 0.1,
+        camera_vfov: 20.0,
+        camera_focus_distance: 10.0,
+        filter: Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 },
+        sampler: SamplerKind::Sobol,
+        seed: arguments.seed,
+        scene_seed: 0,
+        scene_hash: 0,
+        resume_from: arguments.resume_from.clone(),
+        post_process: PostProcess::new(exposure, arguments.tone_mapper.unwrap_or(ToneMapper::Clamp)),
+        progressive_output: if arguments.progress_path.is_some() || arguments.state_path.is_some() {
+            Some(ProgressiveOutput {
+                path: arguments.progress_path.clone(),
+                state_path: arguments.state_path.clone(),
+                write_every_passes: Some(1),
+                write_interval: Some(Duration::from_secs(30)),
+            })
+        } else {
+            None
+        },
+        aov_directory: arguments.aov_directory.clone(),
+        denoiser: if arguments.denoise { Some(Denoiser::default()) } else { None },
+    };
+
+    let scene = match &arguments.scene_path {
+        Some(path) => {
+            // saved states remember which file they are a render of
+            let loaded = fs::read(path).and_then(|bytes| Ok((hash_bytes(&bytes), load_scene(path)?)));
+            let (scene_hash, scene) = loaded.unwrap_or_else(|error| {
+                eprintln!("Failed to load the scene {}: {}", path.display(), error);
+                process::exit(1);
+            });
+            settings.scene_hash = scene_hash;
+            scene
+        },
+        None => scene::random_scene(settings.scene_seed),
+    };
+
+    // render through the scene's own camera, --camera still picks the projection
+    if let Some(camera) = scene.cameras.first() {
+        settings = settings.with_scene_camera(camera);
+        if let Some(projection) = &arguments.projection {
+            settings.camera_projection = projection.clone();
+        }
+    }
+
+    let result = if arguments.preview {
+        preview(&settings, &scene)
+    } else if arguments.merge.is_empty() {
+        render::raytracer(&settings, &scene)
+    } else {
+        merge(&arguments, &settings)
+    };
+
+    let mut film = result.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    // only the final image is denoised, saved states and progress images keep the raw samples
+    if let Some(denoiser) = &settings.denoiser {
+        film = denoiser.denoise(&film);
+        eprintln!("Denoised the image");
+    }
 
-            aa_pixel /= num_aa_samples as f32;
-            let gamma_corrected_pixel = Vector3::new(aa_pixel.r().sqrt(), aa_pixel.g().sqrt(), aa_pixel.b().sqrt());
-            let ir = (255.99 * gamma_corrected_pixel.r()) as i32;
-            let ig = (255.99 * gamma_corrected_pixel.g()) as i32;
-            let ib = (255.99 * gamma_corrected_pixel.b()) as i32;
+    let stdout = io::stdout();
+    film.write_ppm(&settings.post_process, &mut stdout.lock()).expect("failed to write the image");
 
-            println!("{} {} {}", ir, ig, ib);
+    if let Some(adaptive_sampling) = &settings.adaptive_sampling {
+        if let Some(heatmap_path) = &adaptive_sampling.heatmap_path {
+            let mut heatmap = BufWriter::new(File::create(heatmap_path).expect("failed to create the sample heatmap"));
+            film.write_sample_heatmap(adaptive_sampling.max_samples, &mut heatmap).expect("failed to write the sample heatmap");
         }
+    }
 
-        let percent_done = 100.0 * (1.0 - (y as f32 / num_cols as f32));
-        eprintln!("{}% done", percent_done);
+    if let Some(aov_directory) = &settings.aov_directory {
+        film.save_aovs(aov_directory).expect("failed to write the AOVs");
     }
-    eprintln!("Finished generating raytraced image with dimensions X: {}, Y: {}", num_rows, num_cols);
 }
 
-fn main() {
-    let num_rows = 200;
-    let num_cols = 100;
-    let num_aa_samples = 100;
-    let max_depth = 50;
-    let camera_fov = 20.0;
-    let camera_focus_distance = 10.0;
-    let aperture = 0.1;
-    self::raytracer(num_rows, num_cols, num_aa_samples, max_depth, aperture, camera_fov, camera_focus_distance);
-}
\ No newline at end of file
+#[cfg(feature = "preview")]
+fn preview(settings: &RenderSettings, scene: &SceneGraph) -> io::Result<Film> {
+    raytracing::preview::run(settings, scene).map_err(io::Error::other)
+}
+
+#[cfg(not(feature = "preview"))]
+fn preview(_settings: &RenderSettings, _scene: &SceneGraph) -> io::Result<Film> {
+    Err(io::Error::other("this build has no preview window, rebuild with `--features preview`"))
+}
+
+/// Loads a scene file by its extension, a lone PLY or STL model gets a scene built around it
+fn load_scene(path: &Path) -> io::Result<SceneGraph> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+    let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or("model");
+    match extension.as_str() {
+        "gltf" | "glb" => load_gltf(path),
+        "ply" => Ok(scene::model_scene(name, TriangleMesh::from_ply(path)?)),
+        "stl" => Ok(scene::model_scene(name, TriangleMesh::from_stl(path)?)),
+        _ => Err(io::Error::other("unknown scene format, expected .gltf, .glb, .ply or .stl")),
+    }
+}
+
+#[cfg(feature = "gltf")]
+fn load_gltf(path: &Path) -> io::Result<SceneGraph> {
+    scene::import_gltf(path)
+}
+
+#[cfg(not(feature = "gltf"))]
+fn load_gltf(_path: &Path) -> io::Result<SceneGraph> {
+    Err(io::Error::other("this build can't load glTF scenes, rebuild with `--features gltf`"))
+}
+
+/// Combines the saved states of independent renders into one film
+fn merge(arguments: &Arguments, settings: &RenderSettings) -> io::Result<Film> {
+    let (mut film, seeds) = Film::load_state(&arguments.merge[0], settings.filter)?;
+    for path in &arguments.merge[1..] {
+        let (other, other_seeds) = Film::load_state(path, settings.filter)?;
+        film.merge(&seeds, &other, &other_seeds)?;
+        eprintln!("Merged {}", path.display());
+    }
+
+    if let Some(state_path) = &arguments.state_path {
+        film.save_state(&seeds, state_path)?;
+    }
+
+    return Ok(film);
+}
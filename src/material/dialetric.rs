@@ -1,76 +1,61 @@
+use crate::Float;
+use crate::Color;
+use crate::Frame;
 use crate::HitRecord;
 use crate::Material;
+use crate::material::fresnel;
 use crate::MaterialScatter;
 use crate::Ray;
+use crate::Normal3;
+use crate::Sampler;
 use crate::Vector3;
 
-use rand::prelude::*;
-
 /// Dialetric materials are like Water -- they both reflect and refract
 pub struct DialetricMaterial {
     /// https://en.wikipedia.org/wiki/Refractive_index 
     /// Higher values yields more severe "refraction" or sharper angles for the rays going into the material
-    refractive_index: f32,
+    refractive_index: Float,
 }
 
-
-
 impl DialetricMaterial {
-    pub fn new(refractive_index: f32) -> Self {
+    pub fn new(refractive_index: Float) -> Self {
         DialetricMaterial {
             refractive_index: refractive_index
         }
     }
-
-    /// Determines the reflectivity of the material given the angle of approach
-    /// 
-    /// This is an approximation algorithm
-    fn shlick(cosine: f32, refractive_index: f32) -> f32 {
-        let mut r0 = (1.0 - refractive_index) / (1.0 + refractive_index);
-        r0 = r0 * r0;
-        return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
-    }
 }
 
-/// First priority is finding the "Normal that points outward" from the source of light
-/// Then we compute the refraction using Schnell's law
-/// Then we determine if we want to return the reflection or the refraction
+/// First we find which side of the surface the ray comes from, which gives the ratio of refractive indices
+/// Then the exact Fresnel reflectance picks between the reflection and the refraction by Snell's law
 impl Material for DialetricMaterial {
-    fn scatter<'a>(&self, ray: &'a Ray, hit_record: &HitRecord) -> Option<MaterialScatter> {
-        let reflected = Vector3::reflect(ray.direction(), &hit_record.normal);
-        let refraction_differential: f32;
-        let outward_normal: &Vector3;
-        let inverted_normal = -&hit_record.normal;
-        let cosine: f32;
-        if Vector3::dot(ray.direction(), &hit_record.normal) > 0.0 {
-            outward_normal = &inverted_normal;
-            refraction_differential = self.refractive_index;
-            cosine = self.refractive_index * Vector3::dot(ray.direction(), &hit_record.normal) / ray.direction().magnitude();
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        // in shading space the normal is +Z, and the direction is unit length
+        let frame = Frame::from_normal(&hit_record.normal);
+        let incoming = frame.to_local(&Vector3::unit_vector(ray.direction()));
+        let up = Normal3::new(0.0, 0.0, 1.0);
+
+        // the geometric normal decides which side we're on, a perturbed shading normal can lie about that
+        let (eta, normal_toward_ray) = if hit_record.geometric_normal.dot(ray.direction()) > 0.0 {
+            (1.0 / self.refractive_index, -&up)
         } else {
-            outward_normal = &hit_record.normal;
-            refraction_differential = 1.0 / self.refractive_index;
-            cosine = - Vector3::dot(ray.direction(), &hit_record.normal) / ray.direction().magnitude();
-        }
+            (self.refractive_index, up.clone())
+        };
+        let cos_incident = -normal_toward_ray.dot(&incoming);
 
-        let reflect_probability = DialetricMaterial::shlick(cosine, self.refractive_index);
-        let scattered_ray: Ray;
-        if random::<f32>() < reflect_probability {
-            // reflected
-            scattered_ray = Ray::new(hit_record.point.clone(), reflected);
-        } else if let Some(refracted) = Vector3::refract(ray.direction(), outward_normal, refraction_differential) {
-            scattered_ray = Ray::new(hit_record.point.clone(), refracted);
+        let reflected = Vector3::reflect(&incoming, &up);
+        let scattered_direction = if (sampler.get_1d() as Float) < fresnel::dielectric(cos_incident, eta) {
+            reflected
         } else {
-            // rust doesn't yet let you join if lets
-            // if we didn't refract we 100% reflected
-            scattered_ray = Ray::new(hit_record.point.clone(), reflected);
-        }
+            // only None under total internal reflection, which the Fresnel term already reflects all of
+            Vector3::refract(&incoming, &normal_toward_ray, eta).unwrap_or(reflected)
+        };
+        let scattered_ray = hit_record.spawn_ray(frame.to_world(&scattered_direction));
 
         return Some(
             MaterialScatter {
                 ray: scattered_ray,
-                albedo: Vector3::new(1.0, 1.0, 1.0)
+                albedo: Color::white()
             }
         )
     }
 }
-
@@ -0,0 +1,37 @@
+use crate::Color;
+use crate::HitRecord;
+use crate::Material;
+use crate::MaterialScatter;
+use crate::Ray;
+use crate::Sampler;
+use crate::Vector3;
+
+/// A surface that gives off light evenly in every direction off its front, and reflects nothing
+/// Paths only find it by bouncing into it, so it is for lights big enough to be hit (see PunctualLight for the rest)
+pub struct DiffuseLightMaterial {
+    radiance: Color,
+}
+
+impl DiffuseLightMaterial {
+    pub fn new(radiance: Color) -> Self {
+        DiffuseLightMaterial {
+            radiance: radiance,
+        }
+    }
+}
+
+impl Material for DiffuseLightMaterial {
+    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        return None;
+    }
+
+    fn emitted(&self, ray: &Ray, hit_record: &HitRecord) -> Color {
+        // the light goes back the way the ray came
+        let toward_viewer = -Vector3::unit_vector(ray.direction());
+        if hit_record.geometric_normal.dot(&toward_viewer) <= 0.0 {
+            return Color::black();
+        }
+
+        return self.radiance.clone();
+    }
+}
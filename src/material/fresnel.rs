@@ -0,0 +1,133 @@
+//! How much light a smooth boundary between two dielectrics (air, glass, water) reflects, the rest is transmitted
+//!
+//! Same conventions as Vector3::refract: cos_incident is the cosine between the incident direction and the normal
+//! on its side, in [0, 1], and eta is the refractive index on the far side over the index on the incident side
+
+use crate::Float;
+
+/// The exact Fresnel reflectance for unpolarized light, the average of the s and p polarizations
+/// Total internal reflection reflects everything
+pub fn dielectric(cos_incident: Float, eta: Float) -> Float {
+    let cos_incident = cos_incident.clamp(0.0, 1.0);
+    let cos_transmitted = match transmitted_cosine(cos_incident, eta) {
+        Some(cos_transmitted) => cos_transmitted,
+        None => return 1.0,
+    };
+
+    let parallel = (eta * cos_incident - cos_transmitted) / (eta * cos_incident + cos_transmitted);
+    let perpendicular = (cos_incident - eta * cos_transmitted) / (cos_incident + eta * cos_transmitted);
+    return (parallel * parallel + perpendicular * perpendicular) / 2.0;
+}
+
+/// Schlick's approximation of dielectric, cheaper and within a few percent of it
+/// The cosine on the less dense side is the one that goes into the approximation, so leaving glass uses the transmitted cosine
+pub fn schlick(cos_incident: Float, eta: Float) -> Float {
+    let cos_incident = cos_incident.clamp(0.0, 1.0);
+    let cosine = if eta >= 1.0 {
+        cos_incident
+    } else {
+        match transmitted_cosine(cos_incident, eta) {
+            Some(cos_transmitted) => cos_transmitted,
+            None => return 1.0,
+        }
+    };
+
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+}
+
+/// The cosine on the far side by Snell's law, or None for total internal reflection
+fn transmitted_cosine(cos_incident: Float, eta: Float) -> Option<Float> {
+    let sin2_transmitted = (1.0 - cos_incident * cos_incident) / (eta * eta);
+    if sin2_transmitted >= 1.0 {
+        return None;
+    }
+    return Some((1.0 - sin2_transmitted).sqrt());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Normal3;
+    use crate::Vector3;
+
+    const ETAS: [Float; 6] = [1.0, 1.33, 1.5, 2.4, 1.0 / 1.5, 1.0 / 2.4];
+
+    fn cosines() -> impl Iterator<Item = Float> {
+        (0..=100).map(|step| step as Float / 100.0)
+    }
+
+    #[test]
+    fn fresnel_conserves_energy() {
+        for &eta in ETAS.iter() {
+            for cos_incident in cosines() {
+                let reflected = dielectric(cos_incident, eta);
+                assert!((0.0..=1.0).contains(&reflected));
+                assert!((0.0..=1.0).contains(&schlick(cos_incident, eta)));
+
+                // the transmittance from the Fresnel transmission amplitudes, corrected for the change of beam width and speed,
+                // makes up exactly what isn't reflected
+                if let (Some(cos_transmitted), true) = (transmitted_cosine(cos_incident, eta), cos_incident > 0.0) {
+                    let parallel = 2.0 * cos_incident / (eta * cos_incident + cos_transmitted);
+                    let perpendicular = 2.0 * cos_incident / (cos_incident + eta * cos_transmitted);
+                    let transmitted = eta * cos_transmitted / cos_incident * (parallel * parallel + perpendicular * perpendicular) / 2.0;
+                    assert!((reflected + transmitted - 1.0).abs() < 1e-4, "eta {} cos {}: {} + {}", eta, cos_incident, reflected, transmitted);
+                }
+            }
+        }
+
+        // a matched boundary is invisible, and glass reflects about 4% head on
+        assert!(dielectric(0.7, 1.0) < 1e-6);
+        assert!((dielectric(1.0, 1.5) - 0.04).abs() < 1e-4);
+        assert!((schlick(1.0, 1.5) - 0.04).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fresnel_total_internal_reflection() {
+        let eta = 1.0 / 1.5;
+        let critical_sin = eta;
+        let normal = Normal3::new(0.0, 0.0, 1.0);
+        let incident_at = |sin: Float| Vector3::new(sin, 0.0, -(1.0 - sin * sin).sqrt());
+
+        let below = incident_at(critical_sin - 1e-3);
+        assert!(Vector3::refract(&below, &normal, eta).is_some());
+        assert!(dielectric(-below.z(), eta) < 1.0);
+
+        for sin in [critical_sin + 1e-3, 0.8, 0.99].iter() {
+            let beyond = incident_at(*sin);
+            assert!(Vector3::refract(&beyond, &normal, eta).is_none());
+            assert_eq!(1.0, dielectric(-beyond.z(), eta));
+            assert_eq!(1.0, schlick(-beyond.z(), eta));
+        }
+    }
+
+    #[test]
+    fn fresnel_and_refraction_are_reciprocal() {
+        let normal = Normal3::new(0.2, -0.1, 1.0);
+        for &eta in ETAS.iter() {
+            for cos_incident in cosines().skip(1) {
+                let sin_incident = (1.0 - cos_incident * cos_incident).sqrt();
+                // an incident direction at that cosine to a tilted normal
+                let tangent = Vector3::unit_vector(&normal.as_vector().cross(&Vector3::new(1.0, 0.0, 0.0)));
+                let incident = sin_incident * &tangent - cos_incident * normal.as_vector();
+                let refracted = match Vector3::refract(&incident, &normal, eta) {
+                    Some(refracted) => refracted,
+                    None => continue,
+                };
+                assert!((refracted.magnitude() - 1.0).abs() < 1e-4);
+                assert!((-normal.dot(&refracted) - transmitted_cosine(cos_incident, eta).unwrap()).abs() < 1e-4);
+                // Snell's law, and the way back retraces the way in
+                assert!((sin_incident - eta * normal.as_vector().cross(&refracted).magnitude()).abs() < 1e-4);
+                let back = Vector3::refract(&-&refracted, &-&normal, 1.0 / eta).unwrap();
+                assert!((&back + &incident).magnitude() < 1e-3, "eta {} cos {}", eta, cos_incident);
+
+                // the same fraction is reflected either way through the boundary
+                // (from the exact cosine, near grazing angles the reflectance is too steep for the rounding of the refracted vector)
+                let forward = dielectric(cos_incident, eta);
+                let cos_transmitted = transmitted_cosine(cos_incident, eta).unwrap();
+                let backward = dielectric(cos_transmitted, 1.0 / eta);
+                assert!((forward - backward).abs() < 1e-4, "eta {} cos {}: {} vs {}", eta, cos_incident, forward, backward);
+            }
+        }
+    }
+}
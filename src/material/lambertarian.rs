@@ -1,35 +1,97 @@
+use crate::Color;
+use crate::Frame;
 use crate::HitRecord;
 use crate::Material;
 use crate::MaterialScatter;
 use crate::Ray;
+use crate::Sampler;
 use crate::Vector3;
+use crate::sampling::sample_cosine_hemisphere;
 
 /// Lambertarian are "Matte" materials meaning minimal reflectivity
 pub struct LambertarianMaterial {
     /// Albedo is how much energy is "absorbed" by the material
     /// Higher albedo means less absorption
-    albedo: Vector3,
+    /// On meshes with vertex colors, it gets multiplied by the vertex color
+    albedo: Color,
 }
 
 impl LambertarianMaterial {
-    pub fn new(albedo: Vector3) -> Self {
+    pub fn new(albedo: Color) -> Self {
         LambertarianMaterial {
             albedo: albedo
         }
     }
 }
 
+impl LambertarianMaterial {
+    fn albedo_at(&self, hit_record: &HitRecord) -> Color {
+        match &hit_record.vertex_color {
+            Some(vertex_color) => &self.albedo * vertex_color,
+            None => self.albedo.clone(),
+        }
+    }
+}
+
 impl Material for LambertarianMaterial {
-    fn scatter<'a>(&self, _ray: &'a Ray, hit_record: &HitRecord) -> Option<MaterialScatter> {
-        let target: Vector3 = &hit_record.point + &hit_record.normal + Vector3::random_in_unit_sphere();
-        let target_direction = &target - &hit_record.point;
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        // cosine weighted around the normal, which is +Z in shading space
+        // meshes can be hit from behind, so the normal is turned to the side the ray comes from
+        let toward_ray = -ray.direction();
+        let frame = Frame::from_normal(&hit_record.normal.facing(&toward_ray));
+        let target_direction = frame.to_world(&sample_cosine_hemisphere(sampler.get_2d()));
+
+        // a shading normal that leans away from the real surface can still send the ray through it, that light is absorbed
+        if hit_record.geometric_normal.facing(&toward_ray).dot(&target_direction) <= 0.0 {
+            return None;
+        }
+
 
         return Some(
             MaterialScatter {
-                ray: Ray::new(hit_record.point.clone(), target_direction),
-                albedo: self.albedo.clone()
+                ray: hit_record.spawn_ray(target_direction),
+                albedo: self.albedo_at(hit_record)
             }
         )
     }
+
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Color {
+        let toward_ray = -ray.direction();
+        let cosine = hit_record.normal.facing(&toward_ray).dot(direction);
+        if cosine <= 0.0 || hit_record.geometric_normal.facing(&toward_ray).dot(direction) <= 0.0 {
+            return Color::black();
+        }
+
+        return self.albedo_at(hit_record) * (cosine as f32 / std::f32::consts::PI);
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Float;
+    use crate::Hitable;
+    use crate::Mesh;
+    use crate::Point3;
+    use crate::SamplerKind;
+    use crate::TriangleMesh;
+    use std::sync::Arc;
+
+    #[test]
+    fn lambertarian_scatters_back_to_the_side_it_was_hit_from() {
+        // the triangle faces +Z, the ray comes in from behind it
+        let positions = vec![Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0)];
+        let triangle = TriangleMesh::new(positions, vec![[0, 1, 2]]).unwrap();
+        let mesh = Mesh::new(Arc::new(triangle), Arc::new(LambertarianMaterial::new(Color::gray(0.5))));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -1.0), Vector3::new(0.1, 0.0, 1.0));
+        let hit_record = mesh.hit(&ray, 0.0, Float::MAX).unwrap();
+
+        let mut sampler = SamplerKind::Independent.create(256, 0);
+        for _ in 0..256 {
+            let scattered = hit_record.material.scatter(&ray, &hit_record, &mut *sampler).unwrap().ray;
+            assert!(scattered.direction().z() < 0.0);
+            assert!(scattered.origin().z() <= 0.0);
+        }
+    }
+}
@@ -1,24 +1,30 @@
+use crate::Float;
+use crate::Color;
+use crate::Frame;
 use crate::HitRecord;
 use crate::Material;
 use crate::MaterialScatter;
+use crate::Normal3;
 use crate::Ray;
+use crate::Sampler;
+use crate::sampling::sample_uniform_ball;
 use crate::Vector3;
 
 /// Metalic materials are very glossy, meaning extreme reflectivity
 pub struct MetalMaterial {
     /// Albedo is how much energy is "absorbed" by the material
     /// Higher albedo means less absorbtion
-    albedo: Vector3,
+    albedo: Color,
     /// Reflective materials are naturally "Fuzzy"
     /// At first I didn't believe it, but its real. See this pdf
     /// https://pdfs.semanticscholar.org/7233/759231154dd9c8e8c0a6fdaae64f103ab58f.pdf
     /// 
     /// This effectively causes rays to not reflect "perfectly" which causes the distortion we're looking for
-    fuzziness: f32,
+    fuzziness: Float,
 }
 
 impl MetalMaterial {
-    pub fn new(albedo: Vector3, fuzziness: f32) -> Self {
+    pub fn new(albedo: Color, fuzziness: Float) -> Self {
         MetalMaterial {
             albedo: albedo,
             fuzziness: fuzziness
@@ -27,11 +33,16 @@ impl MetalMaterial {
 }
 
 impl Material for MetalMaterial {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<MaterialScatter> {
-        let reflected_direction = Vector3::reflect(&Vector3::unit_vector(ray.direction()), &hit_record.normal);
-        let scattered_ray = Ray::new(hit_record.point.clone(), reflected_direction + self.fuzziness * Vector3::random_in_unit_sphere());
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        // in shading space the normal is +Z
+        let frame = Frame::from_normal(&hit_record.normal);
+        let incoming = frame.to_local(&Vector3::unit_vector(ray.direction()));
+        let reflected = Vector3::reflect(&incoming, &Normal3::new(0.0, 0.0, 1.0));
+        let fuzzed = reflected + self.fuzziness * sample_uniform_ball(sampler.get_2d(), sampler.get_1d());
+        let scattered_ray = hit_record.spawn_ray(frame.to_world(&fuzzed));
 
-        if Vector3::dot(scattered_ray.direction(), &hit_record.normal) < 0f32 {
+        // reflections that end up below the real surface are absorbed, even if the shading normal was perturbed
+        if hit_record.geometric_normal.dot(scattered_ray.direction()) < 0.0 {
             return None;
         }
         
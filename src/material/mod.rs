@@ -1,20 +1,55 @@
-use crate::Vector3;
+use crate::Color;
 use crate::HitRecord;
 use crate::Ray;
+use crate::Sampler;
+use crate::Vector3;
 
 pub use self::dialetric::*;
+pub use self::diffuselight::*;
 pub use self::lambertarian::*;
 pub use self::metal::*;
+pub use self::normalmap::*;
+pub use self::opacitymask::*;
+pub use self::pbr::*;
+
+pub mod fresnel;
 
 mod dialetric;
+mod diffuselight;
 mod lambertarian;
 mod metal;
+mod normalmap;
+mod opacitymask;
+mod pbr;
+
+/// Materials are shared by every object made of them (through an Arc)
+/// They are Send + Sync so a built scene and its material library can be moved to other threads,
+/// the renderer itself doesn't use any threads yet
+pub trait Material: Send + Sync {
+    /// Determines how (and whether) the ray bounces off of the material
+    /// Any random decision takes its numbers from the sampler, so well-distributed samples stay well distributed
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter>;
+
+    /// Cut-out materials are "not there" at some points of the surface
+    /// Hitables check this while intersecting, so rays pass straight through and keep looking for the closest hit
+    fn is_transparent_at(&self, _hit_record: &HitRecord) -> bool {
+        return false;
+    }
+
+    /// Light the surface gives off by itself toward where the ray came from, black for anything that isn't a light
+    fn emitted(&self, _ray: &Ray, _hit_record: &HitRecord) -> Color {
+        return Color::black();
+    }
 
-pub trait Material {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<MaterialScatter>;
+    /// How much of the light arriving from direction (unit length, away from the surface) leaves back along the ray,
+    /// the BSDF times the cosine, which is what lights sampled directly need (see PunctualLight)
+    /// Only diffuse reflection can be evaluated, mirrors and glass are black here and only see lights by scattering into them
+    fn evaluate(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: &Vector3) -> Color {
+        return Color::black();
+    }
 }
 
 pub struct MaterialScatter {
     pub ray: Ray,
-    pub albedo: Vector3
+    pub albedo: Color
 }
\ No newline at end of file
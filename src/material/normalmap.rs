@@ -0,0 +1,156 @@
+use crate::Color;
+use crate::Float;
+use crate::HitRecord;
+use crate::Material;
+use crate::MaterialScatter;
+use crate::Normal3;
+use crate::Ray;
+use crate::Sampler;
+use crate::Texture;
+use crate::Vector3;
+
+use std::sync::Arc;
+
+/// How the shading normal gets perturbed
+pub enum NormalPerturbation {
+    /// A tangent-space normal map: RGB in [0, 1] encodes a normal in [-1, 1] relative to (tangent, bitangent, normal)
+    /// So the "flat" color (0.5, 0.5, 1.0) leaves the normal alone
+    NormalMap(Box<dyn Texture>),
+    /// A height field, only the first (red) channel is read
+    /// The normal is bent by how quickly the height changes along u and v, scaled by `scale`
+    BumpMap {
+        height: Box<dyn Texture>,
+        scale: Float,
+    },
+}
+
+/// Wraps another material and perturbs the shading normal before the wrapped material scatters
+///
+/// The geometric normal is left alone, so materials can still tell which side of the real surface a ray is on
+pub struct NormalMappedMaterial {
+    material: Arc<dyn Material>,
+    perturbation: NormalPerturbation,
+}
+
+impl NormalMappedMaterial {
+    pub fn new(material: Arc<dyn Material>, perturbation: NormalPerturbation) -> Self {
+        NormalMappedMaterial {
+            material: material,
+            perturbation: perturbation,
+        }
+    }
+
+    pub fn normal_map(material: Arc<dyn Material>, normal_map: Box<dyn Texture>) -> Self {
+        NormalMappedMaterial::new(material, NormalPerturbation::NormalMap(normal_map))
+    }
+
+    pub fn bump_map(material: Arc<dyn Material>, height: Box<dyn Texture>, scale: Float) -> Self {
+        NormalMappedMaterial::new(material, NormalPerturbation::BumpMap { height: height, scale: scale })
+    }
+
+    /// Determines the perturbed shading normal for this hit
+    pub fn shading_normal(&self, hit_record: &HitRecord) -> Normal3 {
+        let normal = hit_record.normal.as_vector();
+        let perturbed = match &self.perturbation {
+            NormalPerturbation::NormalMap(texture) => {
+                let encoded = texture.value(hit_record.u, hit_record.v, &hit_record.point);
+                let local = 2.0 * Vector3::new(encoded.r() as Float, encoded.g() as Float, encoded.b() as Float) - 1.0;
+
+                // Gram-Schmidt the tangent against the normal, textures are authored against an orthonormal frame
+                let tangent = Vector3::unit_vector(&(&hit_record.tangent - Vector3::dot(&hit_record.tangent, normal) * normal));
+                let mut bitangent = normal.cross(&tangent);
+                if Vector3::dot(&bitangent, &hit_record.bitangent) < 0.0 {
+                    // mirrored uvs
+                    bitangent = -bitangent;
+                }
+
+                local.x() * tangent + local.y() * bitangent + local.z() * normal
+            },
+            NormalPerturbation::BumpMap { height, scale } => {
+                // Finite differences of the height field, displacing the surface along the normal
+                // P'(u, v) = P(u, v) + height(u, v) * N, so dP'/du ~= dP/du + dheight/du * N
+                let delta = 1.0 / 1024.0;
+                let displacement = scale * height.value(hit_record.u, hit_record.v, &hit_record.point).r() as Float;
+                let u_displacement = scale * height.value(hit_record.u + delta, hit_record.v, &hit_record.point).r() as Float;
+                let v_displacement = scale * height.value(hit_record.u, hit_record.v + delta, &hit_record.point).r() as Float;
+
+                let tangent = &hit_record.tangent + ((u_displacement - displacement) / delta) * normal;
+                let bitangent = &hit_record.bitangent + ((v_displacement - displacement) / delta) * normal;
+                let mut bumped = tangent.cross(&bitangent);
+                if Vector3::dot(&bumped, normal) < 0.0 {
+                    bumped = -bumped;
+                }
+
+                bumped
+            },
+        };
+
+        if perturbed.magnitude_squared() == 0.0 || perturbed.x().is_nan() {
+            // degenerate tangents, nothing sensible to perturb against
+            return hit_record.normal.clone();
+        }
+
+        let perturbed = Normal3::from_vector(&perturbed);
+        if hit_record.geometric_normal.dot(perturbed.as_vector()) < 0.0 && hit_record.geometric_normal.dot(normal) > 0.0 {
+            // Never bend the shading normal behind the real surface, light would leak through it
+            return hit_record.normal.clone();
+        }
+
+        return perturbed;
+    }
+}
+
+impl Material for NormalMappedMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        let mut perturbed_record = hit_record.clone();
+        perturbed_record.normal = self.shading_normal(hit_record);
+        return self.material.scatter(ray, &perturbed_record, sampler);
+    }
+
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Color {
+        let mut perturbed_record = hit_record.clone();
+        perturbed_record.normal = self.shading_normal(hit_record);
+        return self.material.evaluate(ray, &perturbed_record, direction);
+    }
+
+    fn is_transparent_at(&self, hit_record: &HitRecord) -> bool {
+        return self.material.is_transparent_at(hit_record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LambertarianMaterial;
+    use crate::Point3;
+    use crate::SolidTexture;
+
+    fn flat_record(material: &dyn Material) -> HitRecord<'_> {
+        HitRecord::new(1.0, Point3::origin(), Normal3::new(0.0, 0.0, 1.0), (0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), material)
+    }
+
+    #[test]
+    fn normalmap_flat_color_keeps_normal() {
+        let material = NormalMappedMaterial::normal_map(
+            Arc::new(LambertarianMaterial::new(Color::gray(0.5))),
+            Box::new(SolidTexture::new(Color::new(0.5, 0.5, 1.0))));
+        let hit_record = flat_record(&material);
+
+        assert_eq!(Normal3::new(0.0, 0.0, 1.0), material.shading_normal(&hit_record));
+    }
+
+    #[test]
+    fn normalmap_tilts_toward_tangent() {
+        let material = NormalMappedMaterial::normal_map(
+            Arc::new(LambertarianMaterial::new(Color::gray(0.5))),
+            Box::new(SolidTexture::new(Color::new(1.0, 0.5, 1.0))));
+        let hit_record = flat_record(&material);
+
+        let normal = material.shading_normal(&hit_record);
+
+        assert!(normal.x() > 0.5);
+        assert!(normal.y().abs() < 1e-5);
+        assert!((normal.as_vector().magnitude() - 1.0).abs() < 1e-5);
+        assert_eq!(Normal3::new(0.0, 0.0, 1.0), hit_record.geometric_normal);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Color;
+use crate::HitRecord;
+use crate::Material;
+use crate::MaterialScatter;
+use crate::Ray;
+use crate::Sampler;
+use crate::Texture;
+use crate::Vector3;
+
+use std::sync::Arc;
+
+/// Wraps another material and cuts holes in it wherever the opacity texture is below the cutoff
+/// Great for leaves and fences: model a flat shape, then let the texture decide the outline
+///
+/// Only the first (red) channel of the opacity texture is read
+pub struct OpacityMaskMaterial {
+    material: Arc<dyn Material>,
+    opacity: Box<dyn Texture>,
+    /// Texels with opacity below the cutoff are fully transparent, everything else is fully opaque
+    cutoff: f32,
+}
+
+impl OpacityMaskMaterial {
+    pub fn new(material: Arc<dyn Material>, opacity: Box<dyn Texture>, cutoff: f32) -> Self {
+        OpacityMaskMaterial {
+            material: material,
+            opacity: opacity,
+            cutoff: cutoff,
+        }
+    }
+}
+
+impl Material for OpacityMaskMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        return self.material.scatter(ray, hit_record, sampler);
+    }
+
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Color {
+        return self.material.evaluate(ray, hit_record, direction);
+    }
+
+    fn is_transparent_at(&self, hit_record: &HitRecord) -> bool {
+        let opacity = self.opacity.value(hit_record.u, hit_record.v, &hit_record.point).r();
+        return opacity < self.cutoff || self.material.is_transparent_at(hit_record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::Hitable;
+    use crate::HitableList;
+    use crate::LambertarianMaterial;
+    use crate::SolidTexture;
+    use crate::Point3;
+    use crate::Sphere;
+    use crate::Vector3;
+
+    #[test]
+    fn opacitymask_lets_rays_through_to_objects_behind() {
+        let masked = OpacityMaskMaterial::new(
+            Arc::new(LambertarianMaterial::new(Color::gray(0.5))),
+            Box::new(SolidTexture::new(Color::black())),
+            0.5);
+        let world = HitableList::new(vec![
+            Box::new(Sphere::new(Point3::new(3.0, 0.0, 0.0), 1.0, Arc::new(masked))),
+            Box::new(Sphere::new(Point3::new(8.0, 0.0, 0.0), 1.0, Arc::new(LambertarianMaterial::new(Color::gray(0.5))))),
+        ]);
+        let ray = Ray::new(Point3::origin(), Vector3::new(1.0, 0.0, 0.0));
+
+        let hit_record = world.hit(&ray, 0.001, 100.0).unwrap();
+
+        assert_eq!(7.0, hit_record.hit_at);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::Float;
+use crate::Color;
+use crate::Frame;
+use crate::HitRecord;
+use crate::Material;
+use crate::material::fresnel;
+use crate::MaterialScatter;
+use crate::Normal3;
+use crate::Ray;
+use crate::Sampler;
+use crate::sampling::{sample_cosine_hemisphere, sample_uniform_ball};
+use crate::Texture;
+use crate::Vector3;
+
+/// The metallic-roughness material of glTF: metals reflect in their base color, everything else is
+/// the base color diffusely under a thin clear coat (like paint or plastic), which reflects more at grazing angles
+///
+/// Roughness blurs the reflections the same way MetalMaterial's fuzziness does, by roughness squared
+/// The base color is also multiplied by the vertex color of the hit, where the mesh has one
+pub struct PbrMaterial {
+    pub base_color: Color,
+    pub base_color_texture: Option<Box<dyn Texture>>,
+    /// 0 for dielectrics, 1 for metals, in between blends the two
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Multiplies roughness by its green channel and metallic by its blue channel
+    pub metallic_roughness_texture: Option<Box<dyn Texture>>,
+    /// Light given off by the surface
+    pub emissive: Color,
+    pub emissive_texture: Option<Box<dyn Texture>>,
+}
+
+/// The refractive index of the clear coat, 1.5 reflects 4% head on like most dielectrics
+const COAT_REFRACTIVE_INDEX: Float = 1.5;
+
+impl PbrMaterial {
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        PbrMaterial {
+            base_color: base_color,
+            base_color_texture: None,
+            metallic: metallic,
+            roughness: roughness,
+            metallic_roughness_texture: None,
+            emissive: Color::black(),
+            emissive_texture: None,
+        }
+    }
+
+    fn base_color_at(&self, hit_record: &HitRecord) -> Color {
+        let mut base_color = self.base_color.clone();
+        if let Some(texture) = &self.base_color_texture {
+            base_color *= texture.value(hit_record.u, hit_record.v, &hit_record.point);
+        }
+        if let Some(vertex_color) = &hit_record.vertex_color {
+            base_color *= vertex_color.clone();
+        }
+        return base_color;
+    }
+
+    fn metallic_roughness_at(&self, hit_record: &HitRecord) -> (f32, f32) {
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(texture) = &self.metallic_roughness_texture {
+            let value = texture.value(hit_record.u, hit_record.v, &hit_record.point);
+            roughness *= value.g();
+            metallic *= value.b();
+        }
+        return (metallic, roughness);
+    }
+}
+
+impl Material for PbrMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+        let (metallic, roughness) = self.metallic_roughness_at(hit_record);
+
+        // meshes can be hit from behind, shading happens on the side the ray comes from
+        let toward_ray = -ray.direction();
+        let normal = hit_record.normal.facing(&toward_ray);
+        let frame = Frame::from_normal(&normal);
+        let incoming = frame.to_local(&Vector3::unit_vector(ray.direction()));
+        let blur = (roughness * roughness) as Float;
+        let glossy = |sampler: &mut dyn Sampler| Vector3::reflect(&incoming, &Normal3::new(0.0, 0.0, 1.0)) + blur * sample_uniform_ball(sampler.get_2d(), sampler.get_1d());
+
+        let base_color = self.base_color_at(hit_record);
+        let (direction, albedo) = if sampler.get_1d() < metallic {
+            (glossy(sampler), base_color)
+        } else if (sampler.get_1d() as Float) < fresnel::schlick(-incoming.z(), COAT_REFRACTIVE_INDEX) {
+            (glossy(sampler), Color::white())
+        } else {
+            (sample_cosine_hemisphere(sampler.get_2d()), base_color)
+        };
+
+        // reflections that end up below the real surface are absorbed, like MetalMaterial's
+        let direction = frame.to_world(&direction);
+        if hit_record.geometric_normal.facing(&toward_ray).dot(&direction) <= 0.0 {
+            return None;
+        }
+
+        return Some(
+            MaterialScatter {
+                ray: hit_record.spawn_ray(direction),
+                albedo: albedo
+            }
+        )
+    }
+
+    /// Only the diffuse part under the coat, the blurred reflections have no density to evaluate
+    fn evaluate(&self, ray: &Ray, hit_record: &HitRecord, direction: &Vector3) -> Color {
+        let toward_ray = -ray.direction();
+        let normal = hit_record.normal.facing(&toward_ray);
+        let cosine = normal.dot(direction);
+        if cosine <= 0.0 || hit_record.geometric_normal.facing(&toward_ray).dot(direction) <= 0.0 {
+            return Color::black();
+        }
+
+        // the same odds of a diffuse bounce as scatter() has
+        let (metallic, _) = self.metallic_roughness_at(hit_record);
+        let cos_incoming = normal.dot(&Vector3::unit_vector(&toward_ray));
+        let diffuse = (1.0 - metallic) * (1.0 - fresnel::schlick(cos_incoming, COAT_REFRACTIVE_INDEX) as f32);
+        return self.base_color_at(hit_record) * (diffuse * cosine as f32 / std::f32::consts::PI);
+    }
+
+    fn emitted(&self, _ray: &Ray, hit_record: &HitRecord) -> Color {
+        match &self.emissive_texture {
+            Some(texture) => &self.emissive * &texture.value(hit_record.u, hit_record.v, &hit_record.point),
+            None => self.emissive.clone(),
+        }
+    }
+}
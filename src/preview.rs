@@ -0,0 +1,162 @@
+//! A live preview window, only built with `--features preview`
+//!
+//! Shows the image while it refines, one sample per pixel at a time
+//! Drag with the left mouse button to orbit the camera around its look-at point, scroll to move closer or further away
+//! Moving the camera throws away everything rendered so far and starts accumulating again
+//!
+//! Uses a plain software framebuffer (minifb on X11), so it runs on any Linux desktop without a GPU
+
+use crate::render::{ProgressWriter, Renderer};
+use crate::scene::SceneGraph;
+use crate::Film;
+use crate::Float;
+use crate::Point3;
+use crate::RenderSettings;
+use crate::Vector3;
+
+use minifb::{Key, MouseButton, MouseMode, Scale, Window, WindowOptions};
+
+/// Opens the preview window and renders into it until the window is closed (or Escape is pressed)
+/// Returns what was rendered from the last camera position
+pub fn run(settings: &RenderSettings, scene: &SceneGraph) -> Result<Film, String> {
+    let width = settings.width as usize;
+    let height = settings.height as usize;
+    let scale = match width {
+        0..=160 => Scale::X8,
+        161..=320 => Scale::X4,
+        321..=640 => Scale::X2,
+        _ => Scale::X1,
+    };
+
+    let mut window = Window::new("yarrt preview", width, height, WindowOptions { scale: scale, ..WindowOptions::default() })
+        .map_err(|error| format!("failed to open the preview window: {}", error))?;
+    window.set_target_fps(60);
+
+    eprintln!("Building scene");
+    let world = scene.build();
+    eprintln!("Built scene");
+
+    let mut orbit = Orbit::new(&settings.camera_look_from, &settings.camera_look_at);
+    let mut camera = settings.camera();
+    let mut film = Film::new(settings.width, settings.height, settings.filter);
+    let mut sampler = settings.sampler.create(settings.max_samples(), settings.seed);
+    let mut progress_writer = ProgressWriter::new(None, settings.seeds());
+    let mut buffer = vec![0u32; width * height];
+    let mut pass_samples = 1;
+    let mut last_mouse = None;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let mut moved = false;
+        let mouse = window.get_mouse_pos(MouseMode::Pass);
+        if window.get_mouse_down(MouseButton::Left) {
+            if let (Some((last_x, last_y)), Some((x, y))) = (last_mouse, mouse) {
+                if (x, y) != (last_x, last_y) {
+                    orbit.rotate(x - last_x, y - last_y);
+                    moved = true;
+                }
+            }
+        }
+        last_mouse = mouse;
+
+        if let Some((_, scroll)) = window.get_scroll_wheel() {
+            orbit.zoom(scroll);
+            moved = true;
+        }
+
+        if moved {
+            camera = settings.camera_looking_from(orbit.look_from(&settings.camera_look_at));
+            film = Film::new(settings.width, settings.height, settings.filter);
+            pass_samples = 1;
+        }
+
+        if pass_samples <= settings.max_samples() {
+            let renderer = Renderer {
+                settings: settings,
+                camera: &*camera,
+                world: &world,
+            };
+            renderer.render_pass(pass_samples, &mut *sampler, &mut film, &mut progress_writer);
+            window.set_title(&format!("yarrt preview - {} samples per pixel", pass_samples));
+            pass_samples += 1;
+
+            for y in 0..height {
+                for x in 0..width {
+                    // the window's first row is the top of the image, the film counts from the bottom
+                    let [r, g, b] = settings.post_process.apply(&film.pixel(x as u32, (height - 1 - y) as u32));
+                    buffer[y * width + x] = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+                }
+            }
+
+            window.update_with_buffer(&buffer, width, height).map_err(|error| format!("failed to update the preview window: {}", error))?;
+        } else {
+            // done refining, keep the window responsive
+            window.update();
+        }
+    }
+
+    return Ok(film);
+}
+
+/// The camera position in spherical coordinates around the point it looks at
+struct Orbit {
+    radius: Float,
+    /// Angle around the Y axis
+    yaw: Float,
+    /// Angle above the horizon
+    pitch: Float,
+}
+
+impl Orbit {
+    fn new(look_from: &Point3, look_at: &Point3) -> Self {
+        let offset = look_from - look_at;
+        let radius = offset.magnitude();
+        Orbit {
+            radius: radius,
+            yaw: offset.x().atan2(offset.z()),
+            pitch: (offset.y() / radius).asin(),
+        }
+    }
+
+    /// Rotates by a mouse movement, in pixels
+    fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx as Float * 0.01;
+        // stop just short of straight up or down, where "up" would flip around
+        self.pitch = (self.pitch + dy as Float * 0.01).clamp(-1.5, 1.5);
+    }
+
+    /// Moves closer (positive scroll) or further away
+    fn zoom(&mut self, scroll: f32) {
+        self.radius = (self.radius * (0.9 as Float).powf(scroll as Float)).max(0.1);
+    }
+
+    fn look_from(&self, look_at: &Point3) -> Point3 {
+        let offset = Vector3::new(self.yaw.sin() * self.pitch.cos(), self.pitch.sin(), self.yaw.cos() * self.pitch.cos());
+        return look_at + offset * self.radius;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orbit_stops_short_of_the_poles() {
+        let mut orbit = Orbit::new(&Point3::new(0.0, 0.0, 10.0), &Point3::origin());
+        orbit.rotate(0.0, 1000.0);
+        assert_eq!(1.5, orbit.pitch);
+        orbit.rotate(0.0, -5000.0);
+        assert_eq!(-1.5, orbit.pitch);
+    }
+
+    #[test]
+    fn orbit_keeps_its_distance_from_the_look_at() {
+        let look_at = Point3::new(1.0, 2.0, 3.0);
+        let mut orbit = Orbit::new(&Point3::new(4.0, 6.0, 3.0), &look_at);
+        assert!((orbit.look_from(&look_at) - Point3::new(4.0, 6.0, 3.0)).magnitude() < 1e-5);
+
+        for &(dx, dy) in [(30.0, 10.0), (-200.0, 45.0), (90.0, -400.0)].iter() {
+            orbit.rotate(dx, dy);
+            assert!(((orbit.look_from(&look_at) - &look_at).magnitude() - 5.0).abs() < 1e-5);
+        }
+    }
+}
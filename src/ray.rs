@@ -1,23 +1,25 @@
+use crate::Float;
+use crate::Point3;
 use crate::Vector3;
 
 pub struct Ray {
-    origin: Vector3,
+    origin: Point3,
     direction: Vector3
 }
 
 impl Ray {
-    pub fn new(origin: Vector3, direction: Vector3) -> Self {
+    pub fn new(origin: Point3, direction: Vector3) -> Self {
         Ray {
             origin: origin,
             direction: direction
         }
     }
 
-    pub fn point_at_time(&self, t: f32) -> Vector3 {
-        (&self.origin + &(&self.direction * t))
+    pub fn point_at_time(&self, t: Float) -> Point3 {
+        &self.origin + &(&self.direction * t)
     }
 
-    pub fn origin(&self) -> &Vector3 {
+    pub fn origin(&self) -> &Point3 {
         &self.origin
     }
 
@@ -0,0 +1,577 @@
+use crate::sampler::hashing::hash;
+use crate::scene::SceneCamera;
+use crate::scene::SceneGraph;
+use crate::AovSample;
+use crate::AovSurface;
+use crate::Camera;
+use crate::CameraFrame;
+use crate::Color;
+use crate::Denoiser;
+use crate::EquirectangularCamera;
+use crate::FisheyeCamera;
+use crate::Film;
+use crate::Filter;
+use crate::Float;
+use crate::Hitable;
+use crate::HitableList;
+use crate::OrthographicCamera;
+use crate::PerspectiveCamera;
+use crate::Point3;
+use crate::PostProcess;
+use crate::Projection;
+use crate::RenderSeeds;
+use crate::Ray;
+use crate::Sampler;
+use crate::SamplerKind;
+use crate::Vector3;
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Everything needed to render a scene, apart from the scene itself
+pub struct RenderSettings {
+    /// Number of pixels left to right
+    pub width: u32,
+    /// Number of pixels bottom to top
+    pub height: u32,
+    /// Samples per pixel, or the minimum samples per pixel when sampling adaptively
+    pub num_aa_samples: u32,
+    /// Keep sampling noisy pixels, instead of taking num_aa_samples everywhere
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    pub max_ray_depth: u32,
+    /// Paths that bounced this many times may be terminated early by russian roulette, see color()
+    pub russian_roulette_depth: u32,
+    /// Where the camera sits
+    pub camera_look_from: Point3,
+    /// The point the camera looks at
+    pub camera_look_at: Point3,
+    /// Which way is "up" for the camera
+    pub camera_v_up: Vector3,
+    /// Perspective, orthographic, fisheye or panoramic
+    /// The aperture, vfov and focus distance below only matter for the perspective camera
+    pub camera_projection: Projection,
+    pub camera_aperture: Float,
+    pub camera_vfov: Float,
+    pub camera_focus_distance: Float,
+    /// How each sample is spread over the pixels around it
+    pub filter: Filter,
+    /// Where the "random" numbers come from
+    pub sampler: SamplerKind,
+    /// Renders with the same seed are identical, change it to get a different noise pattern
+    pub seed: u64,
+    /// The random scene is generated from this seed (other scenes ignore it)
+    pub scene_seed: u64,
+    /// Tells scene files apart (see hashing::hash_bytes), 0 for the random scene
+    /// Saved states remember it, so a render can't be resumed or merged with one of another scene
+    pub scene_hash: u64,
+    /// Continue the render saved in this state file instead of starting from scratch
+    pub resume_from: Option<PathBuf>,
+    /// How the linear radiance on the Film becomes the final image
+    pub post_process: PostProcess,
+    /// Where to write the best-so-far image while rendering, if anywhere
+    pub progressive_output: Option<ProgressiveOutput>,
+    /// Directory to write the AOVs (albedo, normal, depth...) to as PFM images, if anywhere
+    pub aov_directory: Option<PathBuf>,
+    /// Denoise the final image, guided by the AOVs
+    pub denoiser: Option<Denoiser>,
+}
+
+impl RenderSettings {
+    /// The most samples any pixel will take
+    pub fn max_samples(&self) -> u32 {
+        match &self.adaptive_sampling {
+            Some(adaptive_sampling) => adaptive_sampling.max_samples.max(self.num_aa_samples),
+            None => self.num_aa_samples,
+        }
+    }
+
+    pub fn camera(&self) -> Box<dyn Camera> {
+        self.camera_looking_from(self.camera_look_from.clone())
+    }
+
+    /// The camera from the settings, moved to look_from (still looking at camera_look_at)
+    pub fn camera_looking_from(&self, look_from: Point3) -> Box<dyn Camera> {
+        let aspect_ratio = self.width as Float / self.height as Float;
+        let frame = CameraFrame::look_at(look_from, &self.camera_look_at, &self.camera_v_up);
+        match &self.camera_projection {
+            Projection::Perspective => Box::new(PerspectiveCamera::from_frame(frame, self.camera_vfov, aspect_ratio, self.camera_aperture, self.camera_focus_distance)),
+            Projection::Physical(physical_camera) => Box::new(physical_camera.perspective_camera(frame, aspect_ratio)),
+            Projection::Orthographic { view_height } => Box::new(OrthographicCamera::new(frame, *view_height, aspect_ratio)),
+            Projection::Fisheye { fov } => Box::new(FisheyeCamera::new(frame, *fov, aspect_ratio)),
+            Projection::Equirectangular => Box::new(EquirectangularCamera::new(frame)),
+        }
+    }
+
+    /// Renders through a camera placed in the scene instead, keeping the width and height (and so the aspect ratio)
+    /// Scene cameras are pinholes, everything is in focus
+    pub fn with_scene_camera(mut self, camera: &SceneCamera) -> Self {
+        self.camera_focus_distance = (&camera.look_at - &camera.look_from).magnitude();
+        self.camera_look_from = camera.look_from.clone();
+        self.camera_look_at = camera.look_at.clone();
+        self.camera_v_up = camera.v_up.clone();
+        self.camera_projection = camera.projection.clone();
+        self.camera_vfov = camera.vfov;
+        self.camera_aperture = 0.0;
+        return self;
+    }
+
+    pub fn seeds(&self) -> RenderSeeds {
+        RenderSeeds {
+            scene_seed: self.scene_seed,
+            sampler_seed: self.seed,
+            fingerprint: self.fingerprint(),
+        }
+    }
+
+    /// A hash of everything besides the seeds that decides what the accumulated samples are of:
+    /// the scene file, where the camera is and how it projects, and the filter that spreads the samples
+    fn fingerprint(&self) -> u64 {
+        let bits = |value: Float| (value as f64).to_bits();
+        return hash(&[
+            self.scene_hash,
+            bits(self.camera_look_from.x()), bits(self.camera_look_from.y()), bits(self.camera_look_from.z()),
+            bits(self.camera_look_at.x()), bits(self.camera_look_at.y()), bits(self.camera_look_at.z()),
+            bits(self.camera_v_up.x()), bits(self.camera_v_up.y()), bits(self.camera_v_up.z()),
+            self.camera_projection.fingerprint(),
+            bits(self.camera_aperture),
+            bits(self.camera_vfov),
+            bits(self.camera_focus_distance),
+            self.filter.fingerprint(),
+        ]);
+    }
+}
+
+/// Periodically writes the image rendered so far, so a long render can be stopped early and still be useful
+/// The image is written after every write_every_passes passes, and whenever write_interval has passed since the last write
+pub struct ProgressiveOutput {
+    /// Where to write the image, None to only save the state
+    pub path: Option<PathBuf>,
+    /// Where to save the accumulation state alongside the image, so the render can be resumed if it gets killed
+    pub state_path: Option<PathBuf>,
+    pub write_every_passes: Option<u32>,
+    pub write_interval: Option<Duration>,
+}
+
+/// Adaptive sampling spends samples where the image is still noisy
+/// Every pixel takes num_aa_samples, then keeps sampling in the following passes until its estimated relative error
+/// drops below error_threshold, or it reaches max_samples
+pub struct AdaptiveSampling {
+    pub max_samples: u32,
+    /// 0.05 means "95% sure the pixel is within 5% of its converged value"
+    pub error_threshold: f32,
+    /// Where to write an image of how many samples each pixel took, if anywhere
+    pub heatmap_path: Option<PathBuf>,
+}
+
+/// Determines the Color that this Ray should have in the world
+/// First we determine the nearest object the ray is going to hit in the world
+/// If it hit, and we haven't reached the max hit-depth, re-cast the ray from the hitable's geometric material
+/// If it hit, and we have reached the max hit-depth, the "base-color" is solid black (0,0,0)
+/// If it did not hit, then the Ray has reflected into the background
+/// Surfaces that give off light (see Material::emitted) add their light along the way,
+/// and every diffuse surface on the path adds the light it gets from the punctual lights it can see
+///
+/// This walks the path in a loop instead of recursing, so deep paths can't overflow the stack
+/// throughput is how much of the light at the end of the path survives all the bounces so far (the product of the albedos)
+///
+/// After russian_roulette_depth bounces, paths are randomly terminated with a probability based on their throughput
+/// Survivors are boosted by 1 / survival_probability, so on average the result is the same (unbiased),
+/// but dark paths that barely contribute stop early instead of bouncing until max_depth
+pub fn color(ray: Ray, world: &HitableList, sampler: &mut dyn Sampler, max_depth: u32, russian_roulette_depth: u32) -> Color {
+    let (color, _) = trace_path(ray, world, sampler, max_depth, russian_roulette_depth);
+    return color;
+}
+
+/// Same as color(), but also returns the AOVs of the path: what the first hit looked like,
+/// and whether the light arrived directly (at most one bounce) or indirectly
+pub fn trace_path(ray: Ray, world: &HitableList, sampler: &mut dyn Sampler, max_depth: u32, russian_roulette_depth: u32) -> (Color, AovSample) {
+    let mut aov = AovSample::default();
+
+    let mut ray = ray;
+    let mut throughput = Color::white();
+    let mut radiance = Color::black();
+    // light reaching the camera at most one bounce after leaving a light (or the sky) is direct, the rest indirect
+    let mut add_light = |light: Color, aov: &mut AovSample, depth: u32| {
+        if depth <= 1 {
+            aov.direct += &light;
+        } else {
+            aov.indirect += &light;
+        }
+        radiance += light;
+    };
+    for current_depth in 0..=max_depth {
+        // no epsilon needed, scattered rays start far enough off the surface to never hit it again (see HitRecord::spawn_ray)
+        let hit_record = match world.hit(&ray, 0.0, Float::MAX) {
+            Some(hit_record) => hit_record,
+            None => {
+                let color = throughput * background(&ray);
+                if current_depth == 0 {
+                    aov.albedo = color.clone();
+                }
+
+                add_light(color, &mut aov, current_depth);
+                return (radiance, aov);
+            },
+        };
+
+        if current_depth == 0 {
+            aov.surface = Some(AovSurface {
+                normal: hit_record.normal.clone(),
+                // hit_at is in units of the ray direction, which the camera doesn't normalize
+                depth: (hit_record.hit_at * ray.direction().magnitude()) as f32,
+                position: hit_record.point.clone(),
+                object_id: hit_record.object_id,
+                material_id: hit_record.material_id,
+            });
+        }
+
+        let emitted = hit_record.material.emitted(&ray, &hit_record);
+        if emitted != Color::black() {
+            add_light(&throughput * &emitted, &mut aov, current_depth);
+        }
+
+        if current_depth == max_depth {
+            break;
+        }
+
+        // punctual lights can't be hit, so every surface looks for them directly (next event estimation)
+        for light in world.lights() {
+            let sample = match light.sample(&hit_record.point) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let reflected = hit_record.material.evaluate(&ray, &hit_record, &sample.direction);
+            if reflected == Color::black() {
+                continue;
+            }
+
+            let shadow_ray = hit_record.spawn_ray(sample.direction);
+            if world.hit(&shadow_ray, 0.0, sample.distance).is_none() {
+                add_light(&throughput * &(reflected * sample.irradiance), &mut aov, current_depth + 1);
+            }
+        }
+
+        let scatter_material = match hit_record.material.scatter(&ray, &hit_record, sampler) {
+            Some(scatter_material) => scatter_material,
+            // we've attenuated the ray
+            None => break,
+        };
+
+        if current_depth == 0 {
+            aov.albedo = scatter_material.albedo.clone();
+        }
+
+        throughput *= scatter_material.albedo;
+        if current_depth + 1 >= russian_roulette_depth {
+            // never quite 100%, or bright bouncy paths would never be cut
+            let survival_probability = throughput.max_component().min(0.95);
+            if sampler.get_1d() >= survival_probability {
+                break;
+            }
+
+            throughput /= survival_probability;
+        }
+
+        ray = scatter_material.ray;
+    }
+
+    // passed the depth, attenuated, or terminated by russian roulette
+    return (radiance, aov);
+}
+
+/// Loads a saved state to continue, as long as it is a render of the same image with the same seeds
+fn load_resumable_state(path: &Path, settings: &RenderSettings) -> io::Result<Film> {
+    let (film, seeds) = Film::load_state(path, settings.filter)?;
+    let expected = settings.seeds();
+    if (film.width(), film.height()) != (settings.width, settings.height) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is a {}x{} render, not {}x{}", path.display(), film.width(), film.height(), settings.width, settings.height)));
+    }
+    if seeds.scene_seed != expected.scene_seed || seeds.fingerprint != expected.fingerprint {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is a render of another scene, or through another camera or filter", path.display())));
+    }
+    if seeds.sampler_seed != expected.sampler_seed {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} was rendered with seed {}, not {}", path.display(), seeds.sampler_seed, expected.sampler_seed)));
+    }
+
+    return Ok(film);
+}
+
+/// The sky, a gradient from white at the horizon to blue overhead
+fn background(ray: &Ray) -> Color {
+    let ray_direction_unit = Vector3::unit_vector(ray.direction());
+    let t = 0.5f32 * ray_direction_unit.y() as f32 + 1f32;
+    return &(&Color::white() * (1f32 - t)) + &(&Color::new(0.5, 0.7, 1.0) * t);
+}
+
+/// Renders the scene onto a Film, the linear radiance is left for the caller to post-process
+///
+/// The whole frame is rendered in progressive passes, doubling the samples per pixel every pass (1, 2, 4, 8...)
+/// until every pixel has num_aa_samples (or converged, when sampling adaptively)
+/// With progressive output configured, the best-so-far image is written out along the way
+///
+/// When resuming, every pixel picks up from the samples it already has
+pub fn raytracer(settings: &RenderSettings, scene: &SceneGraph) -> io::Result<Film> {
+    let mut film = match &settings.resume_from {
+        Some(path) => {
+            let film = load_resumable_state(path, settings)?;
+            eprintln!("Resuming from {}", path.display());
+            film
+        },
+        None => Film::new(settings.width, settings.height, settings.filter),
+    };
+
+    eprintln!("Building scene");
+    let world = scene.build();
+    eprintln!("Built scene");
+    let camera = settings.camera();
+    let renderer = Renderer {
+        settings: settings,
+        camera: &*camera,
+        world: &world,
+    };
+
+    let mut sampler = settings.sampler.create(settings.max_samples(), settings.seed);
+    let mut progress_writer = ProgressWriter::new(settings.progressive_output.as_ref(), settings.seeds());
+    let mut pass_samples = 1;
+    loop {
+        let samples_traced = renderer.render_pass(pass_samples, &mut *sampler, &mut film, &mut progress_writer);
+        // passes a resumed render already finished trace nothing
+        if samples_traced > 0 {
+            eprintln!("Finished pass up to {} samples per pixel ({} samples traced)", pass_samples, samples_traced);
+            progress_writer.finished_pass(&film, &settings.post_process);
+        }
+
+        if pass_samples >= settings.max_samples() {
+            break;
+        }
+
+        pass_samples = (pass_samples * 2).min(settings.max_samples());
+    }
+
+    progress_writer.write(&film, &settings.post_process);
+    eprintln!("Finished generating raytraced image with dimensions X: {}, Y: {}", settings.width, settings.height);
+    return Ok(film);
+}
+
+/// What every sample needs to know about the render
+pub(crate) struct Renderer<'a> {
+    pub(crate) settings: &'a RenderSettings,
+    pub(crate) camera: &'a dyn Camera,
+    pub(crate) world: &'a HitableList,
+}
+
+impl<'a> Renderer<'a> {
+    /// Brings every pixel that still needs samples up to pass_samples samples per pixel
+    /// Returns the number of samples traced, which is 0 once every pixel is done
+    pub(crate) fn render_pass(&self, pass_samples: u32, sampler: &mut dyn Sampler, film: &mut Film, progress_writer: &mut ProgressWriter) -> u32 {
+        let mut samples_traced = 0;
+        for y in (0..self.settings.height).rev() {
+            for x in 0..self.settings.width {
+                // the sample count doubles as the index of the next sample, so every sample of a pixel is different
+                while film.statistics(x, y).count() < pass_samples && self.needs_samples(film, x, y) {
+                    let sample_index = film.statistics(x, y).count();
+                    self.trace_sample(x, y, sample_index, sampler, film);
+                    samples_traced += 1;
+                }
+            }
+
+            progress_writer.finished_row(film, &self.settings.post_process);
+        }
+
+        return samples_traced;
+    }
+
+    /// Whether pixel (x, y) should keep sampling
+    fn needs_samples(&self, film: &Film, x: u32, y: u32) -> bool {
+        let statistics = film.statistics(x, y);
+        if statistics.count() < self.settings.num_aa_samples {
+            return true;
+        }
+
+        match &self.settings.adaptive_sampling {
+            Some(adaptive_sampling) => statistics.count() < self.settings.max_samples() && statistics.relative_error() > adaptive_sampling.error_threshold,
+            None => false,
+        }
+    }
+
+    /// Traces sample number sample_index of pixel (x, y) and adds it to the film
+    fn trace_sample(&self, x: u32, y: u32, sample_index: u32, sampler: &mut dyn Sampler, film: &mut Film) {
+        sampler.start_pixel_sample(x, y, sample_index);
+        let offset = sampler.get_2d();
+        let film_x = x as Float + offset.0 as Float;
+        let film_y = y as Float + offset.1 as Float;
+        let (color, aov) = match self.camera.get_ray(film_x / self.settings.width as Float, film_y / self.settings.height as Float, sampler) {
+            Some(ray) => self::trace_path(ray, self.world, sampler, self.settings.max_ray_depth, self.settings.russian_roulette_depth),
+            // still a sample, it's just black
+            None => (Color::black(), AovSample::default()),
+        };
+        film.add_sample(x, y, offset, &color);
+        film.add_aov_sample(x, y, &aov);
+    }
+}
+
+/// Keeps track of when the next progressive image is due
+pub(crate) struct ProgressWriter<'a> {
+    output: Option<&'a ProgressiveOutput>,
+    seeds: RenderSeeds,
+    passes: u32,
+    last_write: Instant,
+}
+
+impl<'a> ProgressWriter<'a> {
+    pub(crate) fn new(output: Option<&'a ProgressiveOutput>, seeds: RenderSeeds) -> Self {
+        ProgressWriter {
+            output: output,
+            seeds: seeds,
+            passes: 0,
+            last_write: Instant::now(),
+        }
+    }
+
+    fn finished_row(&mut self, film: &Film, post_process: &PostProcess) {
+        if let Some(interval) = self.output.and_then(|output| output.write_interval) {
+            if self.last_write.elapsed() >= interval {
+                self.write(film, post_process);
+            }
+        }
+    }
+
+    fn finished_pass(&mut self, film: &Film, post_process: &PostProcess) {
+        self.passes += 1;
+        if let Some(every_passes) = self.output.and_then(|output| output.write_every_passes) {
+            if self.passes.is_multiple_of(every_passes.max(1)) {
+                self.write(film, post_process);
+            }
+        }
+    }
+
+    fn write(&mut self, film: &Film, post_process: &PostProcess) {
+        if let Some(output) = self.output {
+            // a failed progress write shouldn't throw away the render, the next one might work
+            if let Some(path) = &output.path {
+                match film.save_ppm(post_process, path) {
+                    Ok(()) => eprintln!("Wrote progress to {}", path.display()),
+                    Err(error) => eprintln!("Failed to write progress to {}: {}", path.display(), error),
+                }
+            }
+
+            if let Some(state_path) = &output.state_path {
+                if let Err(error) = film.save_state(&self.seeds, state_path) {
+                    eprintln!("Failed to save the render state to {}: {}", state_path.display(), error);
+                }
+            }
+        }
+
+        self.last_write = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HitRecord;
+    use crate::Material;
+    use crate::MaterialScatter;
+    use crate::Sphere;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// The inside of a furnace: glows the same everywhere and bounces every ray straight back through the middle,
+    /// so the only randomness left on a path is russian roulette
+    struct Furnace {
+        albedo: f32,
+        bounces: AtomicU32,
+    }
+
+    impl Material for Furnace {
+        fn scatter(&self, _ray: &Ray, hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> Option<MaterialScatter> {
+            self.bounces.fetch_add(1, Ordering::Relaxed);
+            let ray = hit_record.spawn_ray(&Point3::origin() - &hit_record.point);
+            return Some(MaterialScatter { ray: ray, albedo: Color::gray(self.albedo) });
+        }
+
+        fn emitted(&self, _ray: &Ray, _hit_record: &HitRecord) -> Color {
+            return Color::white();
+        }
+    }
+
+    /// The mean radiance over many paths from the middle of the furnace, and the most bounces any of them took
+    fn furnace(max_depth: u32, russian_roulette_depth: u32) -> (f32, u32) {
+        let material = Arc::new(Furnace { albedo: 0.5, bounces: AtomicU32::new(0) });
+        let world = HitableList::new(vec![Box::new(Sphere::new(Point3::origin(), 1.0, material.clone()))]);
+        let mut sampler = SamplerKind::Independent.create(1, 0);
+
+        let paths = 20000;
+        let (mut total, mut most_bounces) = (0.0, 0);
+        for path in 0..paths {
+            sampler.start_pixel_sample(0, 0, path);
+            material.bounces.store(0, Ordering::Relaxed);
+            let ray = Ray::new(Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
+            total += color(ray, &world, &mut *sampler, max_depth, russian_roulette_depth).r();
+            most_bounces = most_bounces.max(material.bounces.load(Ordering::Relaxed));
+        }
+        return (total / paths as f32, most_bounces);
+    }
+
+    fn settings(scene_hash: u64) -> RenderSettings {
+        RenderSettings {
+            width: 4,
+            height: 2,
+            num_aa_samples: 1,
+            adaptive_sampling: None,
+            max_ray_depth: 4,
+            russian_roulette_depth: 3,
+            camera_look_from: Point3::new(0.0, 0.0, 5.0),
+            camera_look_at: Point3::origin(),
+            camera_v_up: Vector3::new(0.0, 1.0, 0.0),
+            camera_projection: Projection::Perspective,
+            camera_aperture: 0.0,
+            camera_vfov: 40.0,
+            camera_focus_distance: 5.0,
+            filter: Filter::default(),
+            sampler: SamplerKind::Independent,
+            seed: 1,
+            scene_seed: 0,
+            scene_hash: scene_hash,
+            resume_from: None,
+            post_process: PostProcess::default(),
+            progressive_output: None,
+            aov_directory: None,
+            denoiser: None,
+        }
+    }
+
+    #[test]
+    fn resuming_refuses_a_state_of_another_scene_or_camera() {
+        let path = std::env::temp_dir().join(format!("yarrt-resume-test-{}.state", std::process::id()));
+        let saved = settings(1);
+        Film::new(saved.width, saved.height, saved.filter).save_state(&saved.seeds(), &path).unwrap();
+
+        let other_scene = settings(2);
+        let mut other_camera = settings(1);
+        other_camera.camera_projection = Projection::Fisheye { fov: 180.0 };
+        let mut other_filter = settings(1);
+        other_filter.filter = Filter::Tent { radius: 1.0 };
+        for other in [other_scene, other_camera, other_filter].iter() {
+            let error = load_resumable_state(&path, other).err().unwrap();
+            assert!(error.to_string().contains("another scene"), "{}", error);
+        }
+        assert!(load_resumable_state(&path, &settings(1)).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_furnace_estimate() {
+        // every bounce adds the glow times the albedo so far, 1 + 0.5 + 0.25 + ... = 2
+        let max_depth = 64;
+        let (without, without_bounces) = furnace(max_depth, u32::MAX);
+        let (with, with_bounces) = furnace(max_depth, 3);
+
+        assert!((without - 2.0).abs() < 1e-4, "{}", without);
+        assert!((with - 2.0).abs() < 0.05, "{}", with);
+        assert_eq!(max_depth, without_bounces);
+        assert!(with_bounces < max_depth, "{}", with_bounces);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::sampler::hashing::{hash, to_unit_float};
+use crate::Sampler;
+
+/// The Halton sequence: dimension i is the radical inverse of the sample index in the i-th prime base
+/// Each pixel gets its own random digit scrambling (Owen-style, hashed per digit), so neighboring pixels don't share patterns
+///
+/// Only the first PRIMES.len() dimensions are distinct, deeper dimensions reuse the bases with a different scramble
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_seed: u64,
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed: seed,
+            pixel_seed: seed,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample_dimension(&self, dimension: u32) -> f32 {
+        let base = PRIMES[dimension as usize % PRIMES.len()] as u64;
+        let dimension_seed = hash(&[self.pixel_seed, dimension as u64]);
+        return scrambled_radical_inverse(self.sample_index as u64, base, dimension_seed);
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.pixel_seed = hash(&[x as u64, y as u64, self.seed]);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let value = self.sample_dimension(self.dimension);
+        self.dimension += 1;
+        return value;
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let value = (self.sample_dimension(self.dimension), self.sample_dimension(self.dimension + 1));
+        self.dimension += 2;
+        return value;
+    }
+}
+
+/// Mirrors the digits of `index` (written in `base`) around the decimal point, so 0.d0 d1 d2 ...
+/// Every digit is permuted by a hash of the digits before it, which is Owen scrambling in an arbitrary base
+fn scrambled_radical_inverse(mut index: u64, base: u64, seed: u64) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_power = 1.0;
+    let mut prefix = 0u64;
+    let mut value = 0.0f64;
+
+    // 24 bits of precision is all an f32 can hold, stop once the digits stop mattering
+    while inverse_base_power > 1e-8 {
+        let digit = index % base;
+        index /= base;
+        let digit_seed = hash(&[seed, prefix]);
+        let scrambled_digit = (digit + digit_seed % base) % base;
+        inverse_base_power *= inverse_base;
+        value += scrambled_digit as f64 * inverse_base_power;
+        prefix = prefix.wrapping_mul(base).wrapping_add(digit + 1);
+    }
+
+    return (value as f32).min(to_unit_float(u32::MAX));
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
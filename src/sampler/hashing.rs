@@ -0,0 +1,120 @@
+//! Small deterministic building blocks for the samplers
+//! Everything here is a pure function of its inputs, so a sample can be recomputed from (pixel, index, dimension, seed)
+
+/// Finalizer from splitmix64, scrambles all 64 bits
+pub fn mix_bits(mut value: u64) -> u64 {
+    value ^= value >> 31;
+    value = value.wrapping_mul(0x7fb5_d329_728e_a185);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x81da_def4_bc2d_d44d);
+    value ^= value >> 33;
+    return value;
+}
+
+/// Hashes a handful of values into one
+pub fn hash(values: &[u64]) -> u64 {
+    let mut hash = 0x9e37_79b9_7f4a_7c15u64;
+    for value in values {
+        hash = mix_bits(hash ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15).wrapping_add(hash << 6));
+    }
+
+    return hash;
+}
+
+/// Hashes a byte string (64-bit FNV-1a), the same on every platform and build so hashes can be saved
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in bytes {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    return hash;
+}
+
+/// Returns element `index` of a random permutation of [0, length), chosen by `seed`, without building the permutation
+/// Andrew Kensler, "Correlated Multi-Jittered Sampling"
+pub fn permutation_element(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & w) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & w) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & w) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= w;
+        index ^= index >> 5;
+        if index < length {
+            break;
+        }
+    }
+
+    return index.wrapping_add(seed) % length;
+}
+
+/// Owen scrambling of the bits of `value`: each bit is flipped based on a hash of all the bits above it
+/// Keeps the stratification of (0, 2)-sequences like Sobol while decorrelating them
+/// Brent Burley, "Practical Hash-based Owen Scrambling" (Laine-Karras style hash)
+pub fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut value = value.reverse_bits();
+    value ^= value.wrapping_mul(0x3d20_adea);
+    value = value.wrapping_add(seed);
+    value = value.wrapping_mul((seed >> 16) | 1);
+    value ^= value.wrapping_mul(0x0552_6c56);
+    value ^= value.wrapping_mul(0x53a2_2864);
+    return value.reverse_bits();
+}
+
+/// Maps 32 random bits to a float in [0, 1)
+pub fn to_unit_float(bits: u32) -> f32 {
+    // 24 bits is all the precision an f32 mantissa has, using more can round up to 1.0
+    (bits >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+}
+
+/// A PCG32 random number generator (O'Neill, pcg-random.org)
+/// Small, fast and fully described by two integers, which makes it easy to seed deterministically
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        return rng;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(0x5851_f42d_4c95_7f2d).wrapping_add(self.increment);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        return xor_shifted.rotate_right(rotation);
+    }
+
+    pub fn next_f32(&mut self) -> f32 {
+        to_unit_float(self.next_u32())
+    }
+}
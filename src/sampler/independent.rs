@@ -0,0 +1,32 @@
+use crate::sampler::hashing::{hash, Pcg32};
+use crate::Sampler;
+
+/// Every dimension is an independent uniform random number, the same as calling `random::<f32>()`
+/// Still deterministic though: the numbers only depend on the pixel, sample index and seed
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler {
+            seed: seed,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.rng = Pcg32::new(hash(&[x as u64, y as u64, self.seed]), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        (self.rng.next_f32(), self.rng.next_f32())
+    }
+}
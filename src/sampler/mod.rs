@@ -0,0 +1,106 @@
+pub use self::halton::*;
+pub use self::independent::*;
+pub use self::sobol::*;
+pub use self::stratified::*;
+
+pub mod hashing;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+/// Samplers hand out the "random" numbers for every decision made while tracing a path
+///
+/// Each call to get_1d or get_2d consumes the next dimension(s) of the current sample, so decisions should be made
+/// in the same order for every sample: the pixel position first, then the lens, then one set per bounce
+/// Well distributed samplers (stratified, low-discrepancy) spread the samples of a pixel evenly over every dimension,
+/// which converges faster than independent random numbers for the same number of samples
+///
+/// Values are in [0, 1), and only depend on the pixel, the sample index, the dimension, and the sampler's seed
+pub trait Sampler {
+    /// Starts a new sample, resetting to the first dimension
+    fn start_pixel_sample(&mut self, x: u32, y: u32, sample_index: u32);
+
+    fn get_1d(&mut self) -> f32;
+
+    fn get_2d(&mut self) -> (f32, f32);
+}
+
+/// Which Sampler a render uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    /// Creates the sampler, samples_per_pixel is only a hint for the samplers that stratify to a fixed count
+    pub fn create(&self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => {
+                let x_strata = (samples_per_pixel as f32).sqrt().round().max(1.0) as u32;
+                let y_strata = samples_per_pixel.div_ceil(x_strata);
+                Box::new(StratifiedSampler::new(x_strata, y_strata, true, seed))
+            },
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_values_are_in_unit_interval() {
+        for kind in [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol].iter() {
+            let mut sampler = kind.create(16, 7);
+            for sample_index in 0..64 {
+                sampler.start_pixel_sample(3, 5, sample_index);
+                for _ in 0..10 {
+                    let value = sampler.get_1d();
+                    let (x, y) = sampler.get_2d();
+                    assert!((0.0..1.0).contains(&value), "{:?}", kind);
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y), "{:?}", kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sampler_stratifies_2d_dimensions() {
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol].iter() {
+            let mut sampler = kind.create(16, 11);
+            // skip a few dimensions, the padded ones should be stratified too
+            for skipped in 0..3 {
+                let mut strata = [0; 16];
+                for sample_index in 0..16 {
+                    sampler.start_pixel_sample(1, 2, sample_index);
+                    for _ in 0..skipped {
+                        sampler.get_2d();
+                    }
+
+                    let (x, y) = sampler.get_2d();
+                    strata[(y * 4.0) as usize * 4 + (x * 4.0) as usize] += 1;
+                }
+
+                assert_eq!([1; 16], strata, "{:?} at dimension {}", kind, skipped * 2);
+            }
+        }
+    }
+
+    #[test]
+    fn sampler_is_deterministic() {
+        let mut first = SamplerKind::Halton.create(4, 3);
+        let mut second = SamplerKind::Halton.create(4, 3);
+        first.start_pixel_sample(10, 20, 5);
+        second.start_pixel_sample(10, 20, 5);
+
+        assert_eq!(first.get_2d(), second.get_2d());
+        assert_eq!(first.get_1d(), second.get_1d());
+    }
+}
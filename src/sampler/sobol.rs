@@ -0,0 +1,74 @@
+use crate::sampler::hashing::{hash, owen_scramble, to_unit_float};
+use crate::Sampler;
+
+/// Owen-scrambled Sobol sampling, "padded" so any number of dimensions can be drawn
+///
+/// Every 1D or 2D request uses the first two Sobol dimensions, which form a (0, 2)-sequence: any power-of-two prefix
+/// of it is stratified in every elementary interval. Each request shuffles the sample index (an Owen scramble keeps
+/// power-of-two prefixes intact) and scrambles the points with its own seed, so the padded dimensions don't correlate
+/// Brent Burley, "Practical Hash-based Owen Scrambling"
+pub struct SobolSampler {
+    seed: u64,
+    pixel_seed: u64,
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            seed: seed,
+            pixel_seed: seed,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// The seeds for the current dimension: one to shuffle the index, one per output dimension to scramble the point
+    fn seeds(&self) -> [u32; 3] {
+        let dimension_seed = hash(&[self.pixel_seed, self.dimension as u64]);
+        [dimension_seed as u32, (dimension_seed >> 32) as u32, hash(&[dimension_seed]) as u32]
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.pixel_seed = hash(&[x as u64, y as u64, self.seed]);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let [shuffle_seed, x_seed, _] = self.seeds();
+        self.dimension += 1;
+        let index = owen_scramble(self.sample_index, shuffle_seed);
+        return to_unit_float(owen_scramble(index.reverse_bits(), x_seed));
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let [shuffle_seed, x_seed, y_seed] = self.seeds();
+        self.dimension += 2;
+        let index = owen_scramble(self.sample_index, shuffle_seed);
+        return (
+            to_unit_float(owen_scramble(index.reverse_bits(), x_seed)),
+            to_unit_float(owen_scramble(sobol_second_dimension(index), y_seed)),
+        );
+    }
+}
+
+/// The second Sobol dimension (primitive polynomial x + 1), the first one is simply the bit-reversed index
+/// Its direction numbers follow v[i] = v[i - 1] ^ (v[i - 1] >> 1), starting at 0x80000000
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut direction = 1u32 << 31;
+    let mut value = 0;
+    while index != 0 {
+        if index & 1 == 1 {
+            value ^= direction;
+        }
+
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    return value;
+}
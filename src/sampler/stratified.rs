@@ -0,0 +1,83 @@
+use crate::sampler::hashing::{hash, permutation_element, Pcg32};
+use crate::Sampler;
+
+/// Jittered stratified sampling
+/// Each dimension is split into strata, and every sample of a pixel lands in a different stratum
+/// The strata are visited in a different random order per pixel and per dimension, so dimensions don't correlate
+///
+/// Stratification only holds within the first x_strata * y_strata samples of a pixel, after that the pattern repeats
+pub struct StratifiedSampler {
+    x_strata: u32,
+    y_strata: u32,
+    /// Without jitter every sample sits in the center of its stratum
+    jitter: bool,
+    seed: u64,
+    pixel: (u32, u32),
+    sample_index: u32,
+    dimension: u32,
+    rng: Pcg32,
+}
+
+impl StratifiedSampler {
+    pub fn new(x_strata: u32, y_strata: u32, jitter: bool, seed: u64) -> Self {
+        StratifiedSampler {
+            x_strata: x_strata.max(1),
+            y_strata: y_strata.max(1),
+            jitter: jitter,
+            seed: seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+
+    fn samples_per_pixel(&self) -> u32 {
+        self.x_strata * self.y_strata
+    }
+
+    /// Which stratum this sample lands in for the current dimension
+    fn stratum(&self) -> u32 {
+        let permutation_seed = hash(&[self.pixel.0 as u64, self.pixel.1 as u64, self.dimension as u64, self.seed]) as u32;
+        let index = self.sample_index % self.samples_per_pixel();
+        return permutation_element(index, self.samples_per_pixel(), permutation_seed);
+    }
+
+    fn offset(&mut self) -> f32 {
+        if self.jitter {
+            return self.rng.next_f32();
+        }
+
+        return 0.5;
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.pixel = (x, y);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng = Pcg32::new(hash(&[x as u64, y as u64, self.seed]), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let stratum = self.stratum();
+        self.dimension += 1;
+        let offset = self.offset();
+        return ((stratum as f32 + offset) / self.samples_per_pixel() as f32).min(ONE_MINUS_EPSILON);
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let stratum = self.stratum();
+        self.dimension += 2;
+        let (x, y) = (stratum % self.x_strata, stratum / self.x_strata);
+        let (x_offset, y_offset) = (self.offset(), self.offset());
+        return (
+            ((x as f32 + x_offset) / self.x_strata as f32).min(ONE_MINUS_EPSILON),
+            ((y as f32 + y_offset) / self.y_strata as f32).min(ONE_MINUS_EPSILON),
+        );
+    }
+}
+
+/// The largest f32 below 1.0
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
@@ -0,0 +1,322 @@
+//! Warps from uniform samples in [0, 1)^2 to points and directions on common shapes, with the density of each
+//!
+//! Every warp is a direct mapping, no rejection loops: one sample in is always one point out, which keeps
+//! the stratification of well distributed samplers and makes the cost the same every time
+//! Directions are in a local space where Z is "up" (the normal, or the axis of a cone)
+//! PDFs are per unit area for the disk and the triangle, and per unit solid angle for directions
+
+use crate::float::consts;
+use crate::Float;
+use crate::Point3;
+use crate::Vector3;
+
+/// Maps a 2D sample onto the unit disk, uniformly by area
+/// Uses the concentric mapping of Shirley and Chiu, which maps squares to rings and keeps neighboring samples close
+pub fn sample_concentric_disk(sample: (f32, f32)) -> (Float, Float) {
+    let x = 2.0 * sample.0 as Float - 1.0;
+    let y = 2.0 * sample.1 as Float - 1.0;
+    if x == 0.0 && y == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (radius, theta) = if x.abs() > y.abs() {
+        (x, consts::FRAC_PI_4 * (y / x))
+    } else {
+        (y, consts::FRAC_PI_2 - consts::FRAC_PI_4 * (x / y))
+    };
+    return (radius * theta.cos(), radius * theta.sin());
+}
+
+pub fn concentric_disk_pdf() -> Float {
+    consts::FRAC_1_PI
+}
+
+/// Maps a 2D sample to a direction on the +Z hemisphere, with a density proportional to the cosine to +Z
+/// By Malley's method: a uniform point on the disk, projected up onto the hemisphere
+pub fn sample_cosine_hemisphere(sample: (f32, f32)) -> Vector3 {
+    let (x, y) = sample_concentric_disk(sample);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    return Vector3::new(x, y, z);
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: Float) -> Float {
+    cos_theta.max(0.0) * consts::FRAC_1_PI
+}
+
+/// Maps a 2D sample to a direction, uniformly over the whole sphere
+pub fn sample_uniform_sphere(sample: (f32, f32)) -> Vector3 {
+    let z = 1.0 - 2.0 * sample.0 as Float;
+    return direction_around_z(z, sample.1);
+}
+
+pub fn uniform_sphere_pdf() -> Float {
+    1.0 / (4.0 * consts::PI)
+}
+
+/// Maps a 2D sample to a direction, uniformly over the +Z hemisphere
+pub fn sample_uniform_hemisphere(sample: (f32, f32)) -> Vector3 {
+    return direction_around_z(sample.0 as Float, sample.1);
+}
+
+pub fn uniform_hemisphere_pdf() -> Float {
+    1.0 / (2.0 * consts::PI)
+}
+
+/// Maps a 2D sample to a direction, uniformly over the cone around +Z whose half-angle has a cosine of cos_theta_max
+/// Good for sampling a sphere seen from outside, like a spherical light
+pub fn sample_uniform_cone(sample: (f32, f32), cos_theta_max: Float) -> Vector3 {
+    let z = (1.0 - sample.0 as Float) + sample.0 as Float * cos_theta_max;
+    return direction_around_z(z, sample.1);
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: Float) -> Float {
+    1.0 / (2.0 * consts::PI * (1.0 - cos_theta_max))
+}
+
+/// Maps a 2D sample for the direction and a 1D sample for the distance to a point inside the unit sphere, uniformly by volume
+pub fn sample_uniform_ball(direction_sample: (f32, f32), distance_sample: f32) -> Vector3 {
+    return sample_uniform_sphere(direction_sample) * (distance_sample as Float).cbrt();
+}
+
+/// Maps a 2D sample to the barycentric coordinates of a point, uniformly over a triangle's area
+/// The density is 1 / area of the triangle, see uniform_triangle_pdf
+/// Uses the low-distortion mapping from Physically Based Rendering (4th edition), section A.5.5
+pub fn sample_uniform_triangle(sample: (f32, f32)) -> (Float, Float, Float) {
+    let (u, v) = (sample.0 as Float, sample.1 as Float);
+    let (b0, b1) = if u < v {
+        let b0 = u / 2.0;
+        (b0, v - b0)
+    } else {
+        let b1 = v / 2.0;
+        (u - b1, b1)
+    };
+    return (b0, b1, 1.0 - b0 - b1);
+}
+
+pub fn uniform_triangle_pdf(vertices: [&Point3; 3]) -> Float {
+    let area = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0])).magnitude() / 2.0;
+    return 1.0 / area;
+}
+
+/// The solid angle of the triangle between three unit directions, the area it covers on the unit sphere
+/// By the formula of Van Oosterom and Strackee
+pub fn spherical_triangle_area(a: &Vector3, b: &Vector3, c: &Vector3) -> Float {
+    let numerator = Vector3::dot(a, &b.cross(c));
+    let denominator = 1.0 + Vector3::dot(a, b) + Vector3::dot(a, c) + Vector3::dot(b, c);
+    return (2.0 * numerator.atan2(denominator)).abs();
+}
+
+/// Maps a 2D sample to a direction from point towards the triangle, uniformly over the solid angle the triangle covers
+/// Returns the (unit) direction and its density, 1 / the solid angle, or None when the triangle is degenerate as seen from point
+/// This is Arvo's method, "Stratified Sampling of Spherical Triangles" (1995), as written in Physically Based Rendering (4th edition)
+pub fn sample_spherical_triangle(vertices: [&Point3; 3], point: &Point3, sample: (f32, f32)) -> Option<(Vector3, Float)> {
+    let a = Vector3::unit_vector(&(vertices[0] - point));
+    let b = Vector3::unit_vector(&(vertices[1] - point));
+    let c = Vector3::unit_vector(&(vertices[2] - point));
+
+    // normals of the great circles through each edge
+    let n_ab = a.cross(&b);
+    let n_bc = b.cross(&c);
+    let n_ca = c.cross(&a);
+    if n_ab.magnitude_squared() == 0.0 || n_bc.magnitude_squared() == 0.0 || n_ca.magnitude_squared() == 0.0 {
+        return None;
+    }
+    let n_ab = Vector3::unit_vector(&n_ab);
+    let n_bc = Vector3::unit_vector(&n_bc);
+    let n_ca = Vector3::unit_vector(&n_ca);
+
+    // the angles at each corner, their sum minus pi is the area
+    let alpha = angle_between(&n_ab, &-&n_ca);
+    let beta = angle_between(&n_bc, &-&n_ab);
+    let gamma = angle_between(&n_ca, &-&n_bc);
+    let area = alpha + beta + gamma - consts::PI;
+    if area.is_nan() || area <= 0.0 {
+        return None;
+    }
+
+    // the first sample picks the area of the sub-triangle (a, b, c'), which places c' on the edge from a to c
+    let sub_area_plus_pi = consts::PI + sample.0 as Float * area;
+    let (sin_alpha, cos_alpha) = alpha.sin_cos();
+    let (sin_sub, cos_sub) = sub_area_plus_pi.sin_cos();
+    let sin_phi = sin_sub * cos_alpha - cos_sub * sin_alpha;
+    let cos_phi = cos_sub * cos_alpha + sin_sub * sin_alpha;
+    let k1 = cos_phi + cos_alpha;
+    let k2 = sin_phi - sin_alpha * Vector3::dot(&a, &b);
+    let cos_b_sub = (k2 + (k2 * cos_phi - k1 * sin_phi) * cos_alpha) / ((k2 * sin_phi + k1 * cos_phi) * sin_alpha);
+    let cos_b_sub = if cos_b_sub.is_nan() { 1.0 } else { cos_b_sub.clamp(-1.0, 1.0) };
+    let sin_b_sub = (1.0 - cos_b_sub * cos_b_sub).max(0.0).sqrt();
+    let c_sub = cos_b_sub * &a + sin_b_sub * Vector3::unit_vector(&orthogonal_part(&c, &a));
+
+    // the second sample picks how far along the arc from b to c' the direction is
+    let cos_theta = 1.0 - sample.1 as Float * (1.0 - Vector3::dot(&c_sub, &b));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let direction = cos_theta * &b + sin_theta * Vector3::unit_vector(&orthogonal_part(&c_sub, &b));
+    return Some((direction, 1.0 / area));
+}
+
+/// The direction with a Z of z (the cosine to +Z), turned around Z by the sample
+fn direction_around_z(z: Float, sample: f32) -> Vector3 {
+    let ring_radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * consts::PI * sample as Float;
+    return Vector3::new(ring_radius * phi.cos(), ring_radius * phi.sin(), z);
+}
+
+/// The angle between two unit vectors, accurate for nearly parallel and nearly opposite vectors too (acos isn't)
+fn angle_between(lhs: &Vector3, rhs: &Vector3) -> Float {
+    if Vector3::dot(lhs, rhs) < 0.0 {
+        return consts::PI - 2.0 * ((lhs + rhs).magnitude() / 2.0).min(1.0).asin();
+    }
+    return 2.0 * ((rhs - lhs).magnitude() / 2.0).min(1.0).asin();
+}
+
+/// What is left of vector after removing its part along the unit vector axis
+fn orthogonal_part(vector: &Vector3, axis: &Vector3) -> Vector3 {
+    return vector - Vector3::dot(vector, axis) * axis;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::hashing::Pcg32;
+
+    const SAMPLES: usize = 200_000;
+    const RESOLUTION: usize = 24;
+
+    /// Pearson's chi-square test of samples against the density they should follow, on a RESOLUTION^2 grid over bounds
+    /// The expected count of each cell is the density integrated numerically over it,
+    /// and cells expecting fewer than 5 samples are pooled together, as the test needs
+    fn assert_chi_square(name: &str, bounds: [(Float, Float); 2], mut sample: impl FnMut((f32, f32)) -> (Float, Float), pdf: impl Fn(Float, Float) -> Float) {
+        let size = (bounds[0].1 - bounds[0].0, bounds[1].1 - bounds[1].0);
+        let mut observed = vec![0.0; RESOLUTION * RESOLUTION];
+        let mut rng = Pcg32::new(0x5eed, 7);
+        for _ in 0..SAMPLES {
+            let (x, y) = sample((rng.next_f32(), rng.next_f32()));
+            let column = ((x - bounds[0].0) / size.0 * RESOLUTION as Float) as isize;
+            let row = ((y - bounds[1].0) / size.1 * RESOLUTION as Float) as isize;
+            assert!(column >= 0 && row >= 0 && column <= RESOLUTION as isize && row <= RESOLUTION as isize, "{}: ({}, {}) is out of bounds", name, x, y);
+            let (column, row) = ((column as usize).min(RESOLUTION - 1), (row as usize).min(RESOLUTION - 1));
+            observed[row * RESOLUTION + column] += 1.0;
+        }
+
+        let steps = 16;
+        let cell = (size.0 / RESOLUTION as Float, size.1 / RESOLUTION as Float);
+        let step_area = cell.0 * cell.1 / (steps * steps) as Float;
+        let expected: Vec<Float> = (0..RESOLUTION * RESOLUTION).map(|index| {
+            let (column, row) = (index % RESOLUTION, index / RESOLUTION);
+            let mut integral = 0.0;
+            for step in 0..steps * steps {
+                let x = bounds[0].0 + cell.0 * (column as Float + ((step % steps) as Float + 0.5) / steps as Float);
+                let y = bounds[1].0 + cell.1 * (row as Float + ((step / steps) as Float + 0.5) / steps as Float);
+                integral += pdf(x, y) * step_area;
+            }
+            return integral * SAMPLES as Float;
+        }).collect();
+
+        let mut statistic = 0.0;
+        let mut cells = 0;
+        let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+        for (observed, expected) in observed.iter().zip(expected.iter()) {
+            if *expected < 5.0 {
+                pooled_observed += observed;
+                pooled_expected += expected;
+            } else {
+                statistic += (observed - expected) * (observed - expected) / expected;
+                cells += 1;
+            }
+        }
+        if pooled_expected > 0.0 {
+            statistic += (pooled_observed - pooled_expected) * (pooled_observed - pooled_expected) / pooled_expected;
+            cells += 1;
+        } else {
+            assert_eq!(0.0, pooled_observed, "{}: samples where the density is 0", name);
+        }
+
+        // the critical value at a significance level of about 0.0001, by the approximation of Wilson and Hilferty
+        let freedom = (cells - 1) as Float;
+        let z = 3.72;
+        let critical = freedom * (1.0 - 2.0 / (9.0 * freedom) + z * (2.0 / (9.0 * freedom)).sqrt()).powi(3);
+        assert!(statistic < critical, "{}: chi-square {} over {} cells, above the critical value {}", name, statistic, cells, critical);
+    }
+
+    /// Directions are tested on (z, phi), where a cell's area is its solid angle
+    fn direction_to_cell(direction: &Vector3) -> (Float, Float) {
+        (direction.z(), direction.y().atan2(direction.x()))
+    }
+
+    fn cell_to_direction(z: Float, phi: Float) -> Vector3 {
+        let ring_radius = (1.0 - z * z).max(0.0).sqrt();
+        Vector3::new(ring_radius * phi.cos(), ring_radius * phi.sin(), z)
+    }
+
+    const SPHERE: [(Float, Float); 2] = [(-1.0, 1.0), (-consts::PI, consts::PI)];
+
+    #[test]
+    fn sampling_concentric_disk_is_uniform() {
+        assert_chi_square("disk", [(-1.0, 1.0), (-1.0, 1.0)], sample_concentric_disk,
+            |x, y| if x * x + y * y <= 1.0 { concentric_disk_pdf() } else { 0.0 });
+    }
+
+    #[test]
+    fn sampling_hemispheres_and_spheres_match_their_pdfs() {
+        assert_chi_square("cosine hemisphere", SPHERE, |sample| direction_to_cell(&sample_cosine_hemisphere(sample)),
+            |z, _| cosine_hemisphere_pdf(z));
+        assert_chi_square("uniform hemisphere", SPHERE, |sample| direction_to_cell(&sample_uniform_hemisphere(sample)),
+            |z, _| if z >= 0.0 { uniform_hemisphere_pdf() } else { 0.0 });
+        assert_chi_square("uniform sphere", SPHERE, |sample| direction_to_cell(&sample_uniform_sphere(sample)),
+            |_, _| uniform_sphere_pdf());
+
+        let cos_theta_max = 0.3;
+        assert_chi_square("uniform cone", SPHERE, |sample| direction_to_cell(&sample_uniform_cone(sample, cos_theta_max)),
+            |z, _| if z >= cos_theta_max { uniform_cone_pdf(cos_theta_max) } else { 0.0 });
+    }
+
+    #[test]
+    fn sampling_triangle_is_uniform() {
+        let vertices = [Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.5, 0.0), Point3::new(0.5, 1.5, 0.0)];
+        let corners = [&vertices[0], &vertices[1], &vertices[2]];
+        let pdf = uniform_triangle_pdf(corners);
+        let (edge_1, edge_2) = (&vertices[1] - &vertices[0], &vertices[2] - &vertices[0]);
+        let inside = |x: Float, y: Float| {
+            // barycentrics by Cramer's rule, the triangle is in the XY plane
+            let determinant = edge_1.x() * edge_2.y() - edge_2.x() * edge_1.y();
+            let b1 = (x * edge_2.y() - edge_2.x() * y) / determinant;
+            let b2 = (edge_1.x() * y - x * edge_1.y()) / determinant;
+            b1 >= 0.0 && b2 >= 0.0 && b1 + b2 <= 1.0
+        };
+
+        assert_chi_square("triangle", [(0.0, 2.0), (0.0, 1.5)], |sample| {
+            let (b0, b1, b2) = sample_uniform_triangle(sample);
+            let point = b0 * vertices[0].as_vector() + b1 * vertices[1].as_vector() + b2 * vertices[2].as_vector();
+            (point.x(), point.y())
+        }, |x, y| if inside(x, y) { pdf } else { 0.0 });
+    }
+
+    #[test]
+    fn sampling_spherical_triangle_is_uniform() {
+        let vertices = [Point3::new(-1.0, -0.5, 1.0), Point3::new(1.5, -1.0, 0.6), Point3::new(0.2, 1.0, 1.2)];
+        let corners = [&vertices[0], &vertices[1], &vertices[2]];
+        let point = Point3::new(0.1, 0.2, -0.3);
+        let directions: Vec<Vector3> = vertices.iter().map(|vertex| Vector3::unit_vector(&(vertex - &point))).collect();
+        let area = spherical_triangle_area(&directions[0], &directions[1], &directions[2]);
+        let inside = |direction: &Vector3| {
+            (0..3).all(|edge| {
+                let (a, b, c) = (&directions[edge], &directions[(edge + 1) % 3], &directions[(edge + 2) % 3]);
+                let normal = a.cross(b);
+                Vector3::dot(&normal, direction) * Vector3::dot(&normal, c) >= 0.0
+            })
+        };
+
+        assert_chi_square("spherical triangle", SPHERE, |sample| {
+            let (direction, pdf) = sample_spherical_triangle(corners, &point, sample).unwrap();
+            assert!((pdf - 1.0 / area).abs() < 1e-3 * pdf);
+            direction_to_cell(&direction)
+        }, |z, phi| if inside(&cell_to_direction(z, phi)) { 1.0 / area } else { 0.0 });
+    }
+
+    #[test]
+    fn sampling_spherical_triangle_rejects_degenerate_triangles() {
+        let vertices = [Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 1.0), Point3::new(2.0, 0.0, 1.0)];
+        let point = Point3::new(0.0, 0.0, 0.0);
+        assert!(sample_spherical_triangle([&vertices[0], &vertices[1], &vertices[2]], &point, (0.5, 0.5)).is_none());
+    }
+}
@@ -0,0 +1,451 @@
+//! Loads glTF 2.0 scenes (.gltf and .glb), only built with `--features gltf`
+//!
+//! The node hierarchy, triangle meshes, metallic-roughness materials (with their textures), cameras and
+//! punctual lights (KHR_lights_punctual) all become their SceneGraph counterparts
+//! Animations, skins and morph targets are left out, meshes are loaded in their rest pose
+//! Lights ignore their range, they fall off with the square of the distance all the way
+
+use crate::scene::Light;
+use crate::scene::LightKind;
+use crate::scene::MaterialHandle;
+use crate::scene::MaterialLibrary;
+use crate::scene::NodeId;
+use crate::scene::SceneCamera;
+use crate::scene::SceneGraph;
+use crate::scene::Shape;
+use crate::srgb_to_linear;
+use crate::Color;
+use crate::Float;
+use crate::ImageTexture;
+use crate::Material;
+use crate::Normal3;
+use crate::NormalMappedMaterial;
+use crate::OpacityMaskMaterial;
+use crate::PbrMaterial;
+use crate::Point3;
+use crate::Projection;
+use crate::SolidTexture;
+use crate::Texture;
+use crate::Transform;
+use crate::TriangleMesh;
+use crate::Vector3;
+
+use ::gltf::image::Format;
+use ::gltf::khr_lights_punctual::Kind;
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::Mode;
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Loads the default scene of a .gltf or .glb file (or its first scene, if it doesn't name a default)
+pub fn import_gltf<P: AsRef<Path>>(path: P) -> io::Result<SceneGraph> {
+    let (document, buffers, images) = ::gltf::import(path).map_err(gltf_error)?;
+    GltfImporter::new(&document, &buffers, &images).import()
+}
+
+/// Like import_gltf, for a file already read into memory
+/// Buffers and images have to be embedded (as a .glb or data URIs), there's nowhere to look for other files
+pub fn parse_gltf(bytes: &[u8]) -> io::Result<SceneGraph> {
+    let (document, buffers, images) = ::gltf::import_slice(bytes).map_err(gltf_error)?;
+    GltfImporter::new(&document, &buffers, &images).import()
+}
+
+struct GltfImporter<'a> {
+    document: &'a ::gltf::Document,
+    buffers: &'a [::gltf::buffer::Data],
+    images: &'a [::gltf::image::Data],
+    scene: SceneGraph,
+    /// The library handle of every glTF material, by index, then the default material for primitives without one
+    materials: Vec<MaterialHandle>,
+    /// Every primitive built so far, by mesh and primitive index, so nodes instancing a mesh share it
+    meshes: HashMap<(usize, usize), Arc<TriangleMesh>>,
+}
+
+impl<'a> GltfImporter<'a> {
+    fn new(document: &'a ::gltf::Document, buffers: &'a [::gltf::buffer::Data], images: &'a [::gltf::image::Data]) -> Self {
+        GltfImporter {
+            document: document,
+            buffers: buffers,
+            images: images,
+            scene: SceneGraph::new(),
+            materials: Vec::new(),
+            meshes: HashMap::new(),
+        }
+    }
+
+    fn import(mut self) -> io::Result<SceneGraph> {
+        let gltf_scene = self.document.default_scene().or_else(|| self.document.scenes().next())
+            .ok_or_else(|| invalid_data("glTF file has no scenes"))?;
+
+        for material in self.document.materials() {
+            let name = unique_name(&self.scene.materials, material.name(), "material", material.index().unwrap_or(0));
+            let material = self.material(&material)?;
+            self.materials.push(self.scene.materials.add(&name, material)?);
+        }
+        // primitives without a material get the spec's default: white, fully metallic and fully rough
+        let default = Arc::new(PbrMaterial::new(Color::white(), 1.0, 1.0));
+        let default_name = unique_name(&self.scene.materials, Some("default material"), "material", self.materials.len());
+        self.materials.push(self.scene.materials.add(&default_name, default)?);
+
+        for node in gltf_scene.nodes() {
+            self.add_node(&node, None)?;
+        }
+
+        return Ok(self.scene);
+    }
+
+    fn add_node(&mut self, node: &::gltf::Node, parent: Option<NodeId>) -> io::Result<()> {
+        let name = unique_node_name(&self.scene, node.name(), "node", node.index());
+        let transform = node_transform(node)?;
+        let id = self.scene.add_group(&name, parent, transform)?;
+
+        if let Some(mesh) = node.mesh() {
+            let primitives: Vec<_> = mesh.primitives().filter(|primitive| primitive.mode() == Mode::Triangles).collect();
+            if primitives.len() == 1 {
+                self.scene.node_mut(id).shape = Some(self.mesh_shape(mesh.index(), &primitives[0])?);
+            } else {
+                // every primitive has its own material, so each gets its own node
+                for (index, primitive) in primitives.iter().enumerate() {
+                    let shape = self.mesh_shape(mesh.index(), primitive)?;
+                    let primitive_name = unique_node_name(&self.scene, Some(&format!("{} primitive {}", name, index)), "node", node.index());
+                    self.scene.add_node(&primitive_name, Some(id), Transform::identity(), Some(shape))?;
+                }
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let light_name = unique_node_name(&self.scene, light.name(), "light", light.index());
+            let shape = Shape::Light(light_shape(&light));
+            // the node may also hold a mesh, the light gets a child node so the node keeps a single shape
+            if self.scene.node(id).shape.is_some() {
+                self.scene.add_node(&light_name, Some(id), Transform::identity(), Some(shape))?;
+            } else {
+                self.scene.node_mut(id).shape = Some(shape);
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            let camera_name = camera.name().map(str::to_string).unwrap_or_else(|| format!("camera {}", camera.index()));
+            let camera = scene_camera(&camera, camera_name, &self.scene.world_transform(id));
+            self.scene.cameras.push(camera);
+        }
+
+        for child in node.children() {
+            self.add_node(&child, Some(id))?;
+        }
+
+        return Ok(());
+    }
+
+    fn mesh_shape(&mut self, mesh_index: usize, primitive: &::gltf::Primitive) -> io::Result<Shape> {
+        let material = match primitive.material().index() {
+            Some(index) => self.materials[index],
+            None => self.materials[self.materials.len() - 1],
+        };
+
+        let key = (mesh_index, primitive.index());
+        let mesh = match self.meshes.get(&key) {
+            Some(mesh) => mesh.clone(),
+            None => {
+                let mesh = Arc::new(self.triangle_mesh(primitive)?);
+                self.meshes.insert(key, mesh.clone());
+                mesh
+            },
+        };
+        return Ok(Shape::Mesh { mesh: mesh, material: material });
+    }
+
+    fn triangle_mesh(&self, primitive: &::gltf::Primitive) -> io::Result<TriangleMesh> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let positions: Vec<Point3> = reader.read_positions()
+            .ok_or_else(|| invalid_data("glTF primitive has no positions, or they run past the end of their buffer"))?
+            .map(|[x, y, z]| Point3::new(x as Float, y as Float, z as Float))
+            .collect();
+
+        // the reader also comes up empty when the indices don't fit in their buffer
+        let indices: Vec<u32> = match (reader.read_indices(), primitive.indices()) {
+            (Some(indices), _) => indices.into_u32().collect(),
+            (None, Some(_)) => return Err(invalid_data("glTF triangle indices run past the end of their buffer")),
+            (None, None) => (0..positions.len() as u32).collect(),
+        };
+        if !indices.len().is_multiple_of(3) {
+            return Err(invalid_data("glTF triangle indices don't come in threes"));
+        }
+
+        let triangles = indices.chunks(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+        let mut mesh = TriangleMesh::new(positions, triangles)?;
+        if let Some(normals) = reader.read_normals() {
+            mesh = mesh.with_normals(normals.map(|[x, y, z]| Normal3::new(x as Float, y as Float, z as Float)).collect())?;
+        }
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            // glTF puts (0, 0) at the top-left of the image, ImageTexture at the bottom-left
+            mesh = mesh.with_uvs(uvs.into_f32().map(|[u, v]| (u as Float, 1.0 - v as Float)).collect())?;
+        }
+        if let Some(colors) = reader.read_colors(0) {
+            mesh = mesh.with_colors(colors.into_rgb_f32().map(|[r, g, b]| Color::new(r, g, b)).collect())?;
+        }
+        return Ok(mesh);
+    }
+
+    fn material(&self, gltf_material: &::gltf::Material) -> io::Result<Arc<dyn Material>> {
+        let pbr = gltf_material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let mut base = PbrMaterial::new(Color::new(r, g, b), pbr.metallic_factor(), pbr.roughness_factor());
+        if let Some(info) = pbr.base_color_texture() {
+            base.base_color_texture = Some(Box::new(self.texture(&info.texture(), srgb_to_linear)?));
+        }
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            base.metallic_roughness_texture = Some(Box::new(self.texture(&info.texture(), |value| value)?));
+        }
+
+        let [r, g, b] = gltf_material.emissive_factor();
+        base.emissive = &Color::new(r, g, b) * gltf_material.emissive_strength().unwrap_or(1.0);
+        if let Some(info) = gltf_material.emissive_texture() {
+            base.emissive_texture = Some(Box::new(self.texture(&info.texture(), srgb_to_linear)?));
+        }
+
+        let mut material: Arc<dyn Material> = Arc::new(base);
+        if let Some(normal_texture) = gltf_material.normal_texture() {
+            let normal_map = self.texture(&normal_texture.texture(), |value| value)?;
+            material = Arc::new(NormalMappedMaterial::normal_map(material, Box::new(normal_map)));
+        }
+
+        // there's no partial transparency, blending is treated as a mask at half opacity
+        let cutoff = match gltf_material.alpha_mode() {
+            AlphaMode::Opaque => return Ok(material),
+            AlphaMode::Mask => gltf_material.alpha_cutoff().unwrap_or(0.5),
+            AlphaMode::Blend => 0.5,
+        };
+        let opacity: Box<dyn Texture> = match pbr.base_color_texture() {
+            Some(info) => Box::new(self.alpha_texture(&info.texture(), alpha)?),
+            None => Box::new(SolidTexture::new(Color::gray(alpha))),
+        };
+        return Ok(Arc::new(OpacityMaskMaterial::new(material, opacity, cutoff)));
+    }
+
+    /// The color channels of a texture's image, each value decoded to linear by decode
+    fn texture(&self, texture: &::gltf::Texture, decode: fn(f32) -> f32) -> io::Result<ImageTexture> {
+        let image = self.image(texture)?;
+        let channels = image_channels(image)?;
+        let pixels = (0..(image.width * image.height) as usize).map(|index| {
+            let pixel = &channels[index * 4..index * 4 + 4];
+            Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2]))
+        }).collect();
+        return Ok(ImageTexture::new(image.width as usize, image.height as usize, pixels));
+    }
+
+    /// A texture's alpha channel times alpha_factor, as a gray image (OpacityMaskMaterial reads the red channel)
+    fn alpha_texture(&self, texture: &::gltf::Texture, alpha_factor: f32) -> io::Result<ImageTexture> {
+        let image = self.image(texture)?;
+        let channels = image_channels(image)?;
+        let pixels = (0..(image.width * image.height) as usize).map(|index| Color::gray(channels[index * 4 + 3] * alpha_factor)).collect();
+        return Ok(ImageTexture::new(image.width as usize, image.height as usize, pixels));
+    }
+
+    fn image(&self, texture: &::gltf::Texture) -> io::Result<&'a ::gltf::image::Data> {
+        self.images.get(texture.source().index()).ok_or_else(|| invalid_data("glTF texture refers to a missing image"))
+    }
+}
+
+/// Every pixel of the image as RGBA in [0, 1], missing channels filled in the way glTF samples them
+fn image_channels(image: &::gltf::image::Data) -> io::Result<Vec<f32>> {
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let pixel_count = (image.width * image.height) as usize;
+    if image.pixels.len() < pixel_count * channels * bytes_per_channel {
+        return Err(invalid_data("glTF image is smaller than its size says"));
+    }
+
+    let value = |index: usize| -> f32 {
+        let bytes = &image.pixels[index * bytes_per_channel..(index + 1) * bytes_per_channel];
+        match bytes_per_channel {
+            1 => bytes[0] as f32 / 255.0,
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+            _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    };
+
+    let mut rgba = Vec::with_capacity(pixel_count * 4);
+    for pixel in 0..pixel_count {
+        for channel in 0..4 {
+            rgba.push(match channel {
+                _ if channel < channels => value(pixel * channels + channel),
+                3 => 1.0,
+                // a gray image is gray in every color channel
+                _ if channels <= 2 => value(pixel * channels),
+                _ => 0.0,
+            });
+        }
+    }
+    return Ok(rgba);
+}
+
+/// glTF matrices are column-major, Transform's are row-major
+fn node_transform(node: &::gltf::Node) -> io::Result<Transform> {
+    let columns = node.transform().matrix();
+    let mut matrix = [[0.0; 4]; 4];
+    for (row, values) in matrix.iter_mut().enumerate() {
+        for (column, value) in values.iter_mut().enumerate() {
+            *value = columns[column][row] as Float;
+        }
+    }
+
+    Transform::from_matrix(matrix).ok_or_else(|| invalid_data(&format!("glTF node {} has a transform that can't be inverted", node.index())))
+}
+
+fn light_shape(light: &::gltf::khr_lights_punctual::Light) -> Light {
+    let kind = match light.kind() {
+        Kind::Point => LightKind::Point,
+        Kind::Spot { inner_cone_angle, outer_cone_angle } => LightKind::Spot { inner_cone_angle: inner_cone_angle, outer_cone_angle: outer_cone_angle },
+        Kind::Directional => LightKind::Directional,
+    };
+    let [r, g, b] = light.color();
+    Light {
+        kind: kind,
+        color: Color::new(r, g, b),
+        intensity: light.intensity(),
+    }
+}
+
+/// glTF cameras look down their node's -Z axis, with +Y up
+fn scene_camera(camera: &::gltf::Camera, name: String, world_transform: &Transform) -> SceneCamera {
+    let look_from = world_transform.point(&Point3::origin());
+    let look_at = &look_from + Vector3::unit_vector(&world_transform.vector(&Vector3::new(0.0, 0.0, -1.0)));
+    let v_up = world_transform.vector(&Vector3::new(0.0, 1.0, 0.0));
+    let (projection, vfov) = match camera.projection() {
+        ::gltf::camera::Projection::Perspective(perspective) => (Projection::Perspective, perspective.yfov().to_degrees() as Float),
+        ::gltf::camera::Projection::Orthographic(orthographic) => (Projection::Orthographic { view_height: 2.0 * orthographic.ymag() as Float }, 90.0),
+    };
+
+    SceneCamera {
+        name: name,
+        look_from: look_from,
+        look_at: look_at,
+        v_up: v_up,
+        projection: projection,
+        vfov: vfov,
+    }
+}
+
+/// The name if it is free, otherwise something made unique with the index
+fn unique_name(materials: &MaterialLibrary, name: Option<&str>, kind: &str, index: usize) -> String {
+    make_unique(name, kind, index, |candidate| materials.find(candidate).is_some())
+}
+
+fn unique_node_name(scene: &SceneGraph, name: Option<&str>, kind: &str, index: usize) -> String {
+    make_unique(name, kind, index, |candidate| scene.find(candidate).is_some())
+}
+
+fn make_unique(name: Option<&str>, kind: &str, index: usize, taken: impl Fn(&str) -> bool) -> String {
+    let mut candidate = name.map(str::to_string).unwrap_or_else(|| format!("{} {}", kind, index));
+    let mut suffix = 1;
+    while taken(&candidate) {
+        candidate = format!("{} ({} {}.{})", name.unwrap_or(kind), kind, index, suffix);
+        suffix += 1;
+    }
+    return candidate;
+}
+
+fn gltf_error(error: ::gltf::Error) -> io::Error {
+    match error {
+        ::gltf::Error::Io(error) => error,
+        error => invalid_data(&format!("failed to load glTF: {}", error)),
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hitable;
+    use crate::Ray;
+
+    /// A red triangle on a table, lit by a lamp and seen by a camera, with the buffer embedded as a data URI
+    const TABLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "point", "color": [1.0, 0.5, 0.0], "intensity": 20.0 }] } },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 3] }],
+        "nodes": [
+            { "name": "table", "translation": [0.0, 0.0, -5.0], "children": [1, 2] },
+            { "name": "triangle", "mesh": 0 },
+            { "name": "lamp", "translation": [0.0, 2.0, 0.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+            { "name": "eye", "translation": [0.0, 0.0, 5.0], "camera": 0 }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.5, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+        "materials": [{ "name": "red", "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.0, 0.0, 1.0], "metallicFactor": 0.0 } }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "buffers": [{ "byteLength": 44, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=" }]
+    }"#;
+
+    #[test]
+    fn gltf_imports_nodes_materials_cameras_and_lights() {
+        let scene = parse_gltf(TABLE.as_bytes()).unwrap();
+        let table = scene.find("table").unwrap();
+        let triangle = scene.find("triangle").unwrap();
+        assert_eq!(Some(table), scene.node(triangle).parent());
+        let red = scene.materials.find("red").unwrap();
+        assert!(matches!(&scene.node(triangle).shape, Some(Shape::Mesh { material, .. }) if *material == red));
+        match &scene.node(scene.find("lamp").unwrap()).shape {
+            Some(Shape::Light(light)) => assert_eq!((LightKind::Point, 20.0), (light.kind.clone(), light.intensity)),
+            _ => panic!("the lamp should be a light"),
+        }
+
+        let camera = &scene.cameras[0];
+        assert_eq!(Point3::new(0.0, 0.0, 5.0), camera.look_from);
+        assert!((camera.look_at.z() - 4.0).abs() < 1e-5);
+        assert!((camera.vfov - (0.5 as Float).to_degrees()).abs() < 1e-3);
+
+        // the triangle sits on the table, 5 units back
+        let world = scene.build();
+        let hit = world.hit(&Ray::new(Point3::new(0.2, 0.2, 10.0), Vector3::new(0.0, 0.0, -1.0)), 0.0, Float::MAX).unwrap();
+        assert!((hit.point.z() + 5.0).abs() < 1e-4);
+        assert_eq!(1, hit.material_id);
+        assert!(world.hit(&Ray::new(Point3::new(0.8, 0.8, 10.0), Vector3::new(0.0, 0.0, -1.0)), 0.0, Float::MAX).is_none());
+    }
+
+    #[test]
+    fn gltf_instanced_meshes_are_built_once() {
+        let instanced = TABLE.replace("{ \"name\": \"eye\",", "{ \"name\": \"copy\", \"mesh\": 0 }, { \"name\": \"eye\",")
+            .replace("\"children\": [1, 2]", "\"children\": [1, 2, 3]").replace("\"nodes\": [0, 3]", "\"nodes\": [0, 4]");
+        let scene = parse_gltf(instanced.as_bytes()).unwrap();
+        let mesh = |name: &str| match &scene.node(scene.find(name).unwrap()).shape {
+            Some(Shape::Mesh { mesh, .. }) => mesh.clone(),
+            _ => panic!("{} should be a mesh", name),
+        };
+        assert!(Arc::ptr_eq(&mesh("triangle"), &mesh("copy")));
+    }
+
+    #[test]
+    fn gltf_reports_broken_files() {
+        assert_eq!(Some(io::ErrorKind::InvalidData), parse_gltf(b"{ \"asset\": ").err().map(|error| error.kind()));
+        let out_of_range = TABLE.replace("\"count\": 3, \"type\": \"SCALAR\"", "\"count\": 30, \"type\": \"SCALAR\"");
+        assert!(parse_gltf(out_of_range.as_bytes()).is_err());
+    }
+}
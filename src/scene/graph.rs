@@ -0,0 +1,381 @@
+use crate::Color;
+use crate::Float;
+use crate::Hitable;
+use crate::HitableList;
+use crate::Material;
+use crate::Mesh;
+use crate::Point3;
+use crate::Projection;
+use crate::PunctualLight;
+use crate::Sphere;
+use crate::SpotCone;
+use crate::Transform;
+use crate::TransformedHitable;
+use crate::TriangleMesh;
+use crate::Vector3;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// Refers to a node of a SceneGraph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// Refers to a material of a MaterialLibrary, any number of shapes can use the same material
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(usize);
+
+/// A node or material was added under a name that's already taken, names have to be unique
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateName(pub String);
+
+impl fmt::Display for DuplicateName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "there already is something named {}", self.0)
+    }
+}
+
+impl Error for DuplicateName {}
+
+impl From<DuplicateName> for io::Error {
+    fn from(error: DuplicateName) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// The materials of a scene, each one named and shared by every shape that uses it
+/// Replacing a material changes it for all of those shapes at once
+#[derive(Default)]
+pub struct MaterialLibrary {
+    materials: Vec<(String, Arc<dyn Material>)>,
+    names: HashMap<String, MaterialHandle>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        MaterialLibrary {
+            materials: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Material names are unique, a second material by the same name isn't added
+    pub fn add(&mut self, name: &str, material: Arc<dyn Material>) -> Result<MaterialHandle, DuplicateName> {
+        if self.names.contains_key(name) {
+            return Err(DuplicateName(name.to_string()));
+        }
+        let handle = MaterialHandle(self.materials.len());
+        self.materials.push((name.to_string(), material));
+        self.names.insert(name.to_string(), handle);
+        return Ok(handle);
+    }
+
+    pub fn find(&self, name: &str) -> Option<MaterialHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn get(&self, handle: MaterialHandle) -> &Arc<dyn Material> {
+        &self.materials[handle.0].1
+    }
+
+    pub fn replace(&mut self, handle: MaterialHandle, material: Arc<dyn Material>) {
+        self.materials[handle.0].1 = material;
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+/// The geometry a node holds, in the node's own space
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Sphere { center: Point3, radius: Float, material: MaterialHandle },
+    /// The triangles are shared, so the same mesh can be placed by many nodes
+    Mesh { mesh: Arc<TriangleMesh>, material: MaterialHandle },
+    Light(Light),
+}
+
+/// A light at the node's origin, built into a PunctualLight that lights every surface it can see
+/// Point and spot lights give off intensity (candela) times color, directional lights shine with an irradiance (lux) of intensity times color
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightKind {
+    Point,
+    /// Shines down the node's -Z axis, fully bright inside the inner cone and fading out to the outer cone
+    /// Angles are in radians from the axis
+    Spot { inner_cone_angle: f32, outer_cone_angle: f32 },
+    /// Shines down the node's -Z axis from infinitely far away
+    Directional,
+}
+
+/// A camera placed in the scene, see RenderSettings::with_scene_camera to render through it
+#[derive(Clone)]
+pub struct SceneCamera {
+    pub name: String,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub v_up: Vector3,
+    pub projection: Projection,
+    /// Only used by the perspective projection
+    pub vfov: Float,
+}
+
+/// A named node, placed relative to its parent by its transform
+/// Nodes without a shape are groups, that move or hide everything under them together
+pub struct SceneNode {
+    name: String,
+    parent: Option<NodeId>,
+    /// Node to parent space, the world for nodes without a parent
+    pub transform: Transform,
+    pub shape: Option<Shape>,
+    /// Hidden nodes and everything under them are left out when the scene is built
+    pub hidden: bool,
+}
+
+impl SceneNode {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+}
+
+/// A scene as a hierarchy of named nodes, with the materials their shapes share
+///
+/// Parents are always added before their children, so every node comes after its parent
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<SceneNode>,
+    names: HashMap<String, NodeId>,
+    pub materials: MaterialLibrary,
+    /// Cameras don't take part in building the scene, the renderer's camera comes from the RenderSettings
+    pub cameras: Vec<SceneCamera>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        SceneGraph {
+            nodes: Vec::new(),
+            names: HashMap::new(),
+            materials: MaterialLibrary::new(),
+            cameras: Vec::new(),
+        }
+    }
+
+    /// Node names are unique, a second node by the same name isn't added
+    pub fn add_node(&mut self, name: &str, parent: Option<NodeId>, transform: Transform, shape: Option<Shape>) -> Result<NodeId, DuplicateName> {
+        if self.names.contains_key(name) {
+            return Err(DuplicateName(name.to_string()));
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(SceneNode {
+            name: name.to_string(),
+            parent: parent,
+            transform: transform,
+            shape: shape,
+            hidden: false,
+        });
+        self.names.insert(name.to_string(), id);
+        return Ok(id);
+    }
+
+    /// A node without a shape, to group other nodes under
+    pub fn add_group(&mut self, name: &str, parent: Option<NodeId>, transform: Transform) -> Result<NodeId, DuplicateName> {
+        self.add_node(name, parent, transform, None)
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.names.get(name).copied()
+    }
+
+    pub fn node(&self, id: NodeId) -> &SceneNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        &mut self.nodes[id.0]
+    }
+
+    /// The node by that name, to change before the scene is built
+    pub fn node_named_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        let id = self.find(name)?;
+        Some(self.node_mut(id))
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        self.nodes.iter().enumerate().map(|(index, node)| (NodeId(index), node))
+    }
+
+    /// Node to world, the node's transform followed by the transforms of all its parents
+    pub fn world_transform(&self, id: NodeId) -> Transform {
+        let node = self.node(id);
+        match node.parent {
+            Some(parent) => node.transform.then(&self.world_transform(parent)),
+            None => node.transform.clone(),
+        }
+    }
+
+    /// Whether the node or any of its parents is hidden
+    pub fn is_hidden(&self, id: NodeId) -> bool {
+        let node = self.node(id);
+        node.hidden || node.parent.is_some_and(|parent| self.is_hidden(parent))
+    }
+
+    /// The hitables of every visible shape and the visible lights, placed in the world
+    /// Shapes that share a material share its material ID, the material's place in the library starting at 1
+    pub fn build(&self) -> HitableList {
+        // parents come first, so their world transforms are known by the time their children need them
+        let mut world_transforms: Vec<Transform> = Vec::with_capacity(self.nodes.len());
+        let mut hidden: Vec<bool> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            let (transform, parent_hidden) = match node.parent {
+                Some(NodeId(parent)) => (node.transform.then(&world_transforms[parent]), hidden[parent]),
+                None => (node.transform.clone(), false),
+            };
+            world_transforms.push(transform);
+            hidden.push(parent_hidden || node.hidden);
+        }
+
+        let mut hitables: Vec<Box<dyn Hitable>> = Vec::new();
+        let mut material_ids = Vec::new();
+        let mut lights = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if hidden[index] {
+                continue;
+            }
+
+            let transform = &world_transforms[index];
+            if let Some(Shape::Light(light)) = &node.shape {
+                lights.push(build_light(light, transform));
+            } else if let Some(shape) = &node.shape {
+                let (hitable, material) = self.build_shape(shape);
+                if *transform == Transform::identity() {
+                    hitables.push(hitable);
+                } else {
+                    hitables.push(Box::new(TransformedHitable::new(hitable, transform.clone())));
+                }
+                material_ids.push(material.0 as u32 + 1);
+            }
+        }
+
+        return HitableList::with_material_ids(hitables, material_ids).with_lights(lights);
+    }
+
+    fn build_shape(&self, shape: &Shape) -> (Box<dyn Hitable>, MaterialHandle) {
+        match shape {
+            Shape::Sphere { center, radius, material } => {
+                let sphere = Sphere::new(center.clone(), *radius, self.materials.get(*material).clone());
+                (Box::new(sphere), *material)
+            },
+            Shape::Mesh { mesh, material } => {
+                let mesh = Mesh::new(mesh.clone(), self.materials.get(*material).clone());
+                (Box::new(mesh), *material)
+            },
+            Shape::Light(_) => unreachable!("lights are built by build_light"),
+        }
+    }
+}
+
+/// The light in world space, scaling the node moves it but doesn't change its brightness or cone
+fn build_light(light: &Light, transform: &Transform) -> PunctualLight {
+    let position = transform.point(&Point3::origin());
+    let axis = Vector3::unit_vector(&transform.vector(&Vector3::new(0.0, 0.0, -1.0)));
+    let intensity = &light.color * light.intensity;
+    match light.kind {
+        LightKind::Point => PunctualLight::Point { position: position, intensity: intensity },
+        LightKind::Spot { inner_cone_angle, outer_cone_angle } => PunctualLight::Spot {
+            position: position,
+            intensity: intensity,
+            cone: SpotCone { axis: axis, cos_inner: inner_cone_angle.cos(), cos_outer: outer_cone_angle.cos() },
+        },
+        LightKind::Directional => PunctualLight::Directional { direction: axis, irradiance: intensity },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::LambertarianMaterial;
+    use crate::MetalMaterial;
+    use crate::Ray;
+    use crate::Vector3;
+
+    fn table_scene() -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let matte = scene.materials.add("matte", Arc::new(LambertarianMaterial::new(Color::gray(0.5)))).unwrap();
+        let table = scene.add_group("table", None, Transform::translation(&Vector3::new(0.0, 0.0, -10.0))).unwrap();
+        let ball = Shape::Sphere { center: Point3::origin(), radius: 1.0, material: matte };
+        scene.add_node("left ball", Some(table), Transform::translation(&Vector3::new(-3.0, 0.0, 0.0)), Some(ball.clone())).unwrap();
+        scene.add_node("right ball", Some(table), Transform::translation(&Vector3::new(3.0, 0.0, 0.0)), Some(ball)).unwrap();
+        return scene;
+    }
+
+    fn hit_at(world: &HitableList, x: Float) -> Option<u32> {
+        let ray = Ray::new(Point3::new(x, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        world.hit(&ray, 0.0, Float::MAX).map(|hit_record| hit_record.material_id)
+    }
+
+    #[test]
+    fn scene_children_follow_their_parents() {
+        let mut scene = table_scene();
+        let world = scene.build();
+        assert_eq!(Some(1), hit_at(&world, -3.0));
+        assert_eq!(Some(1), hit_at(&world, 3.0));
+        assert_eq!(None, hit_at(&world, 0.0));
+
+        let table = scene.find("table").unwrap();
+        scene.node_mut(table).transform = Transform::translation(&Vector3::new(1.0, 0.0, -10.0));
+        let right_ball = scene.find("right ball").unwrap();
+        assert!((scene.world_transform(right_ball).point(&Point3::origin()).x() - 4.0).abs() < 1e-5);
+        assert_eq!(Some(1), hit_at(&scene.build(), 4.5));
+    }
+
+    #[test]
+    fn scene_hides_and_changes_nodes_by_name() {
+        let mut scene = table_scene();
+        scene.node_named_mut("left ball").unwrap().hidden = true;
+        let world = scene.build();
+        assert_eq!(None, hit_at(&world, -3.0));
+        assert_eq!(Some(1), hit_at(&world, 3.0));
+
+        // hiding the group hides everything on it
+        scene.node_named_mut("table").unwrap().hidden = true;
+        assert_eq!(None, hit_at(&scene.build(), 3.0));
+        assert!(scene.node_named_mut("chair").is_none());
+
+        scene.node_named_mut("table").unwrap().hidden = false;
+        let shiny = scene.materials.add("shiny", Arc::new(MetalMaterial::new(Color::gray(0.9), 0.0))).unwrap();
+        if let Some(Shape::Sphere { material, .. }) = &mut scene.node_named_mut("right ball").unwrap().shape {
+            *material = shiny;
+        }
+        assert_eq!(Some(2), hit_at(&scene.build(), 3.0));
+    }
+
+    #[test]
+    fn scene_refuses_names_that_are_taken() {
+        let mut scene = table_scene();
+        let duplicate = scene.add_group("table", None, Transform::identity());
+        assert_eq!(Err(DuplicateName("table".to_string())), duplicate);
+        let matte = scene.materials.add("matte", Arc::new(LambertarianMaterial::new(Color::gray(0.1))));
+        assert_eq!(Err(DuplicateName("matte".to_string())), matte);
+        assert_eq!(3, scene.nodes().count());
+        assert_eq!(1, scene.materials.len());
+    }
+}
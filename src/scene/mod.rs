@@ -0,0 +1,14 @@
+//! Scenes are described as a graph of named nodes, then built into the hitables that get rendered
+//! Until it is built, anything in the scene can be looked up by name, hidden, moved or given another material
+
+#[cfg(feature = "gltf")]
+pub use self::gltf::*;
+pub use self::graph::*;
+pub use self::model::*;
+pub use self::random::*;
+
+#[cfg(feature = "gltf")]
+mod gltf;
+mod graph;
+mod model;
+mod random;
@@ -0,0 +1,74 @@
+use crate::scene::SceneCamera;
+use crate::scene::SceneGraph;
+use crate::scene::Shape;
+use crate::Color;
+use crate::Float;
+use crate::LambertarianMaterial;
+use crate::Point3;
+use crate::Projection;
+use crate::Transform;
+use crate::TriangleMesh;
+use crate::Vector3;
+
+use std::sync::Arc;
+
+/// A scene of just one model (say a scan or a part to print), matte and lit by the sky
+/// Vertex colors show through as the albedo, and a camera frames the whole model from the front, a little above
+/// A mesh without any vertices gets the camera that would frame a unit box around the origin
+pub fn model_scene(name: &str, mesh: TriangleMesh) -> SceneGraph {
+    let mut scene = SceneGraph::new();
+    let material = scene.materials.add(name, Arc::new(LambertarianMaterial::new(Color::gray(0.8)))).expect("a new scene has no materials yet");
+
+    let (mut min, mut max) = match mesh.positions().first() {
+        Some(position) => (position.clone(), position.clone()),
+        None => (Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5)),
+    };
+    for position in mesh.positions() {
+        min = Point3::new(min.x().min(position.x()), min.y().min(position.y()), min.z().min(position.z()));
+        max = Point3::new(max.x().max(position.x()), max.y().max(position.y()), max.z().max(position.z()));
+    }
+    let center = Point3::new((min.x() + max.x()) / 2.0, (min.y() + max.y()) / 2.0, (min.z() + max.z()) / 2.0);
+    let radius = ((&max - &min).magnitude() / 2.0).max(1e-3);
+
+    // far enough back that the sphere around the model fits in the view
+    let vfov: Float = 40.0;
+    let distance = radius / (vfov / 2.0).to_radians().sin();
+    scene.cameras.push(SceneCamera {
+        name: "front".to_string(),
+        look_from: &center + Vector3::unit_vector(&Vector3::new(0.3, 0.4, 1.0)) * distance,
+        look_at: center,
+        v_up: Vector3::new(0.0, 1.0, 0.0),
+        projection: Projection::Perspective,
+        vfov: vfov,
+    });
+
+    scene.add_node(name, None, Transform::identity(), Some(Shape::Mesh { mesh: Arc::new(mesh), material: material })).expect("a new scene has no nodes yet");
+    return scene;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_camera_frames_the_whole_model() {
+        let positions = vec![Point3::new(1.0, 2.0, 3.0), Point3::new(5.0, 2.0, 3.0), Point3::new(1.0, 6.0, 7.0)];
+        let scene = model_scene("wedge", TriangleMesh::new(positions.clone(), vec![[0, 1, 2]]).unwrap());
+        let camera = &scene.cameras[0];
+        assert_eq!(Point3::new(3.0, 4.0, 5.0), camera.look_at);
+
+        // every vertex is inside the vertical field of view, looking straight at the middle
+        let forward = Vector3::unit_vector(&(&camera.look_at - &camera.look_from));
+        for position in positions.iter() {
+            let toward = Vector3::unit_vector(&(position - &camera.look_from));
+            assert!(Vector3::dot(&forward, &toward).min(1.0).acos().to_degrees() <= camera.vfov / 2.0);
+        }
+    }
+
+    #[test]
+    fn model_without_vertices_still_gets_a_camera() {
+        let scene = model_scene("nothing", TriangleMesh::new(Vec::new(), Vec::new()).unwrap());
+        assert_eq!(Point3::origin(), scene.cameras[0].look_at);
+        assert!(scene.cameras[0].look_from.as_vector().magnitude() > 1.0);
+    }
+}
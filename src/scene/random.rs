@@ -0,0 +1,78 @@
+use crate::material::*;
+use crate::scene::DuplicateName;
+use crate::scene::MaterialHandle;
+use crate::scene::SceneGraph;
+use crate::scene::Shape;
+use crate::Color;
+use crate::Float;
+use crate::Point3;
+use crate::Transform;
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use std::sync::Arc;
+
+/// The scene is random, but the same seed always generates the same scene
+///
+/// The small spheres are grouped under "small spheres" and named by their spot on the grid, like "small sphere -3,7"
+/// The big ones are "glass sphere", "matte sphere" and "metal sphere", on top of the "ground"
+pub fn random_scene(seed: u64) -> SceneGraph {
+    build_random_scene(seed).expect("every name in the random scene is unique")
+}
+
+fn build_random_scene(seed: u64) -> Result<SceneGraph, DuplicateName> {
+    let mut scene = SceneGraph::new();
+    let ground = scene.materials.add("ground", Arc::new(LambertarianMaterial::new(Color::gray(0.5))))?;
+    scene.add_node("ground", None, Transform::identity(), sphere(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground))?;
+
+    // all the glass is the same glass, every other sphere gets a material of its own
+    let glass = scene.materials.add("glass", Arc::new(DialetricMaterial::new(1.5)))?;
+
+    let small_spheres = scene.add_group("small spheres", None, Transform::identity())?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    for a in -11..11 {
+        for b in -11..11 {
+            let material = rng.gen::<f32>();
+            let center = Point3::new(a as Float + 0.9 * rng.gen::<f32>() as Float, 0.2, b as Float + 0.9 * rng.gen::<f32>() as Float);
+
+            if (&center - Point3::new(4.0, 0.2, 0.0)).magnitude() > 0.9 {
+                let name = format!("small sphere {},{}", a, b);
+                let material = match material {
+                    x if x.in_range(0.0, 0.8) => {
+                        scene.materials.add(&name, Arc::new(LambertarianMaterial::new(Color::new(rng.gen::<f32>() * rng.gen::<f32>(), rng.gen::<f32>() * rng.gen::<f32>(), rng.gen::<f32>() * rng.gen::<f32>()))))?
+                    },
+                    x if x.in_range(0.8, 0.95) => {
+                        scene.materials.add(&name, Arc::new(MetalMaterial::new(Color::new(0.5 * (1.0 + rng.gen::<f32>()), 0.5 * (1.0 + rng.gen::<f32>()), 0.5 * (1.0 + rng.gen::<f32>())), 0.5 * rng.gen::<f32>() as Float)))?
+                    },
+                    _ => glass,
+                };
+
+                scene.add_node(&name, Some(small_spheres), Transform::identity(), sphere(center, 0.2, material))?;
+            }
+        }
+    }
+
+    // Add three bigger spheres because they look cool
+    scene.add_node("glass sphere", None, Transform::identity(), sphere(Point3::new(0.0, 1.0, 0.0), 1.0, glass))?;
+    let matte = scene.materials.add("matte sphere", Arc::new(LambertarianMaterial::new(Color::new(0.4, 0.2, 0.1))))?;
+    scene.add_node("matte sphere", None, Transform::identity(), sphere(Point3::new(-4.0, 1.0, 0.0), 1.0, matte))?;
+    let metal = scene.materials.add("metal sphere", Arc::new(MetalMaterial::new(Color::new(0.7, 0.6, 0.5), 0.0)))?;
+    scene.add_node("metal sphere", None, Transform::identity(), sphere(Point3::new(4.0, 1.0, 0.0), 1.0, metal))?;
+    return Ok(scene);
+}
+
+fn sphere(center: Point3, radius: Float, material: MaterialHandle) -> Option<Shape> {
+    Some(Shape::Sphere { center: center, radius: radius, material: material })
+}
+
+// https://stackoverflow.com/questions/49037111/alternatives-to-matching-floating-point-ranges
+trait InRange {
+    fn in_range(&self, begin: Self, end: Self) -> bool;
+}
+
+impl InRange for f32 {
+    fn in_range(&self, begin: f32, end: f32) -> bool {
+        *self >= begin && *self < end
+    }
+}
@@ -0,0 +1,222 @@
+use std::ops;
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Four f32 lanes, operated on together
+#[derive(Clone, Copy)]
+pub struct F32x4 {
+    #[cfg(target_arch = "x86_64")]
+    lanes: __m128,
+    #[cfg(not(target_arch = "x86_64"))]
+    lanes: [f32; 4],
+}
+
+/// The result of comparing two F32x4 lane by lane
+#[derive(Clone, Copy)]
+pub struct Mask4 {
+    #[cfg(target_arch = "x86_64")]
+    lanes: __m128,
+    #[cfg(not(target_arch = "x86_64"))]
+    lanes: [bool; 4],
+}
+
+// The SSE intrinsics are only unsafe because they need the CPU to support SSE, which every x86_64 CPU does
+#[cfg(target_arch = "x86_64")]
+impl F32x4 {
+    pub fn new(a: f32, b: f32, c: f32, d: f32) -> Self {
+        F32x4 { lanes: unsafe { _mm_setr_ps(a, b, c, d) } }
+    }
+
+    pub fn splat(value: f32) -> Self {
+        F32x4 { lanes: unsafe { _mm_set1_ps(value) } }
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        let mut array = [0.0; 4];
+        unsafe { _mm_storeu_ps(array.as_mut_ptr(), self.lanes) };
+        return array;
+    }
+
+    pub fn sqrt(self) -> Self {
+        F32x4 { lanes: unsafe { _mm_sqrt_ps(self.lanes) } }
+    }
+
+    pub fn min(self, other: F32x4) -> Self {
+        F32x4 { lanes: unsafe { _mm_min_ps(self.lanes, other.lanes) } }
+    }
+
+    pub fn max(self, other: F32x4) -> Self {
+        F32x4 { lanes: unsafe { _mm_max_ps(self.lanes, other.lanes) } }
+    }
+
+    pub fn lt(self, other: F32x4) -> Mask4 {
+        Mask4 { lanes: unsafe { _mm_cmplt_ps(self.lanes, other.lanes) } }
+    }
+
+    pub fn le(self, other: F32x4) -> Mask4 {
+        Mask4 { lanes: unsafe { _mm_cmple_ps(self.lanes, other.lanes) } }
+    }
+
+    pub fn gt(self, other: F32x4) -> Mask4 {
+        other.lt(self)
+    }
+
+    /// Takes the lanes of if_true where the mask is set, and of if_false elsewhere
+    pub fn select(mask: Mask4, if_true: F32x4, if_false: F32x4) -> Self {
+        F32x4 { lanes: unsafe { _mm_or_ps(_mm_and_ps(mask.lanes, if_true.lanes), _mm_andnot_ps(mask.lanes, if_false.lanes)) } }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Mask4 {
+    pub fn and(self, other: Mask4) -> Self {
+        Mask4 { lanes: unsafe { _mm_and_ps(self.lanes, other.lanes) } }
+    }
+
+    pub fn or(self, other: Mask4) -> Self {
+        Mask4 { lanes: unsafe { _mm_or_ps(self.lanes, other.lanes) } }
+    }
+
+    /// One bit per lane, lane 0 in the lowest bit
+    pub fn bits(self) -> u32 {
+        unsafe { _mm_movemask_ps(self.lanes) as u32 }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl F32x4 {
+    pub fn new(a: f32, b: f32, c: f32, d: f32) -> Self {
+        F32x4 { lanes: [a, b, c, d] }
+    }
+
+    pub fn splat(value: f32) -> Self {
+        F32x4 { lanes: [value; 4] }
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        self.lanes
+    }
+
+    pub fn sqrt(self) -> Self {
+        self.map(f32::sqrt)
+    }
+
+    pub fn min(self, other: F32x4) -> Self {
+        self.zip(other, f32::min)
+    }
+
+    pub fn max(self, other: F32x4) -> Self {
+        self.zip(other, f32::max)
+    }
+
+    pub fn lt(self, other: F32x4) -> Mask4 {
+        self.compare(other, |a, b| a < b)
+    }
+
+    pub fn le(self, other: F32x4) -> Mask4 {
+        self.compare(other, |a, b| a <= b)
+    }
+
+    pub fn gt(self, other: F32x4) -> Mask4 {
+        other.lt(self)
+    }
+
+    /// Takes the lanes of if_true where the mask is set, and of if_false elsewhere
+    pub fn select(mask: Mask4, if_true: F32x4, if_false: F32x4) -> Self {
+        F32x4 { lanes: std::array::from_fn(|lane| if mask.lanes[lane] { if_true.lanes[lane] } else { if_false.lanes[lane] }) }
+    }
+
+    fn map(self, function: impl Fn(f32) -> f32) -> Self {
+        F32x4 { lanes: self.lanes.map(function) }
+    }
+
+    fn zip(self, other: F32x4, function: impl Fn(f32, f32) -> f32) -> Self {
+        F32x4 { lanes: std::array::from_fn(|lane| function(self.lanes[lane], other.lanes[lane])) }
+    }
+
+    fn compare(self, other: F32x4, function: impl Fn(f32, f32) -> bool) -> Mask4 {
+        Mask4 { lanes: std::array::from_fn(|lane| function(self.lanes[lane], other.lanes[lane])) }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl Mask4 {
+    pub fn and(self, other: Mask4) -> Self {
+        Mask4 { lanes: std::array::from_fn(|lane| self.lanes[lane] && other.lanes[lane]) }
+    }
+
+    pub fn or(self, other: Mask4) -> Self {
+        Mask4 { lanes: std::array::from_fn(|lane| self.lanes[lane] || other.lanes[lane]) }
+    }
+
+    /// One bit per lane, lane 0 in the lowest bit
+    pub fn bits(self) -> u32 {
+        self.lanes.iter().enumerate().map(|(lane, set)| (*set as u32) << lane).sum()
+    }
+}
+
+impl Mask4 {
+    pub fn any(self) -> bool {
+        self.bits() != 0
+    }
+
+    pub fn lane(self, lane: usize) -> bool {
+        self.bits() & (1 << lane) != 0
+    }
+}
+
+macro_rules! F32x4_op {
+    ($trait: ident, $function: ident, $intrinsic: ident, $operator: tt) => {
+        impl ops::$trait for F32x4 {
+            type Output = F32x4;
+
+            #[cfg(target_arch = "x86_64")]
+            fn $function(self, rhs: F32x4) -> F32x4 {
+                F32x4 { lanes: unsafe { $intrinsic(self.lanes, rhs.lanes) } }
+            }
+
+            #[cfg(not(target_arch = "x86_64"))]
+            fn $function(self, rhs: F32x4) -> F32x4 {
+                self.zip(rhs, |a, b| a $operator b)
+            }
+        }
+    };
+}
+
+F32x4_op!(Add, add, _mm_add_ps, +);
+F32x4_op!(Sub, sub, _mm_sub_ps, -);
+F32x4_op!(Mul, mul, _mm_mul_ps, *);
+F32x4_op!(Div, div, _mm_div_ps, /);
+
+impl ops::Neg for F32x4 {
+    type Output = F32x4;
+
+    fn neg(self) -> F32x4 {
+        F32x4::splat(0.0) - self
+    }
+}
+
+impl std::fmt::Debug for F32x4 {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "F32x4{:?}", self.to_array())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32x4_matches_scalar_math() {
+        let a = F32x4::new(1.0, 4.0, -2.0, 9.0);
+        let b = F32x4::new(2.0, 2.0, 3.0, 3.0);
+
+        assert_eq!([3.0, 6.0, 1.0, 12.0], (a + b).to_array());
+        assert_eq!([0.5, 2.0, -2.0 / 3.0, 3.0], (a / b).to_array());
+        assert_eq!([1.0, 2.0, -2.0, 3.0], a.min(b).to_array());
+        assert_eq!(0b0101, a.lt(b).bits());
+        assert_eq!([2.0, 4.0, 3.0, 9.0], F32x4::select(a.lt(b), b, a).to_array());
+        assert_eq!([1.0, 2.0], [a.sqrt().to_array()[0], a.sqrt().to_array()[1]]);
+    }
+}
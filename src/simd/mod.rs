@@ -0,0 +1,13 @@
+//! Four-wide SIMD math, for intersecting packets of four rays at once
+//!
+//! The renderer doesn't trace packets, it still shoots one ray at a time. Only the intersection kernels
+//! (Hitable::hit_packet for lists and spheres, Aabb::hit_packet) use this, benches/intersection.rs measures them against hit()
+//!
+//! On x86_64 the lanes live in an SSE register (SSE2 is part of the x86_64 baseline, so no runtime detection is needed),
+//! everywhere else they fall back to a plain array the compiler can still auto-vectorize
+
+pub use self::f32x4::*;
+pub use self::vector3x4::*;
+
+mod f32x4;
+mod vector3x4;
@@ -0,0 +1,69 @@
+use crate::simd::F32x4;
+use crate::Ray;
+use crate::Vector3;
+
+/// Four Vector3s, stored as one F32x4 per axis (rounded to f32 with the f64 feature) (structure of arrays) so every lane does the same work
+#[derive(Clone, Copy, Debug)]
+pub struct Vector3x4 {
+    pub x: F32x4,
+    pub y: F32x4,
+    pub z: F32x4,
+}
+
+impl Vector3x4 {
+    pub fn new(vectors: [&Vector3; 4]) -> Self {
+        let [a, b, c, d] = vectors;
+        Vector3x4 {
+            x: F32x4::new(a.x() as f32, b.x() as f32, c.x() as f32, d.x() as f32),
+            y: F32x4::new(a.y() as f32, b.y() as f32, c.y() as f32, d.y() as f32),
+            z: F32x4::new(a.z() as f32, b.z() as f32, c.z() as f32, d.z() as f32),
+        }
+    }
+
+    /// The same vector in every lane
+    pub fn splat(vector: &Vector3) -> Self {
+        Vector3x4 {
+            x: F32x4::splat(vector.x() as f32),
+            y: F32x4::splat(vector.y() as f32),
+            z: F32x4::splat(vector.z() as f32),
+        }
+    }
+
+    pub fn dot(&self, other: &Vector3x4) -> F32x4 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn sub(&self, other: &Vector3x4) -> Vector3x4 {
+        Vector3x4 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+/// Four rays traced together
+pub struct RayPacket4<'a> {
+    pub rays: [&'a Ray; 4],
+    pub origin: Vector3x4,
+    pub direction: Vector3x4,
+    /// 1 / direction, for the slab test against bounding boxes
+    pub inverse_direction: Vector3x4,
+}
+
+impl<'a> RayPacket4<'a> {
+    pub fn new(rays: [&'a Ray; 4]) -> Self {
+        let direction = Vector3x4::new([rays[0].direction(), rays[1].direction(), rays[2].direction(), rays[3].direction()]);
+        let one = F32x4::splat(1.0);
+        RayPacket4 {
+            origin: Vector3x4::new([rays[0].origin().as_vector(), rays[1].origin().as_vector(), rays[2].origin().as_vector(), rays[3].origin().as_vector()]),
+            inverse_direction: Vector3x4 {
+                x: one / direction.x,
+                y: one / direction.y,
+                z: one / direction.z,
+            },
+            direction: direction,
+            rays: rays,
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::Color;
+use crate::Float;
+use crate::Point3;
+use crate::Texture;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An Image texture maps (u, v) onto a grid of pixels
+/// u runs left to right and v runs bottom to top, so (0, 0) is the lower-left corner of the image
+///
+/// Values are returned as-is in the range [0, 1] (no gamma decoding), which is what normal maps and height maps want
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Row-major pixels, starting from the top row like the image file itself
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(width * height, pixels.len(), "image texture needs exactly width * height pixels");
+        ImageTexture {
+            width: width,
+            height: height,
+            pixels: pixels,
+        }
+    }
+
+    /// Loads a PPM image, either plain-text (P3) or binary (P6)
+    /// PPM is what the raytracer writes, so it is the one format we can read without pulling in an image library
+    pub fn from_ppm<P: AsRef<Path>>(path: P) -> io::Result<ImageTexture> {
+        let bytes = fs::read(path)?;
+        ImageTexture::parse_ppm(&bytes)
+    }
+
+    pub fn parse_ppm(bytes: &[u8]) -> io::Result<ImageTexture> {
+        let mut cursor = 0;
+        let magic = next_token(bytes, &mut cursor)?;
+        let width = parse_number(next_token(bytes, &mut cursor)?)?;
+        let height = parse_number(next_token(bytes, &mut cursor)?)?;
+        let max_value = parse_number(next_token(bytes, &mut cursor)?)? as f32;
+        if max_value <= 0.0 || max_value > 255.0 {
+            return Err(invalid_data("only 8-bit PPM images are supported"));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(invalid_data("PPM image has no pixels"));
+        }
+
+        // the header's size can't be trusted, every value takes at least a byte so the data has to be at least that long
+        let pixel_count = width.checked_mul(height).filter(|count| count.checked_mul(3).is_some())
+            .ok_or_else(|| invalid_data("PPM image is too large"))?;
+        if bytes.len() - cursor < pixel_count * 3 {
+            return Err(invalid_data("PPM image data is truncated"));
+        }
+
+        let mut pixels = Vec::with_capacity(pixel_count);
+        match magic {
+            b"P3" => {
+                for _ in 0..pixel_count {
+                    let r = parse_number(next_token(bytes, &mut cursor)?)? as f32;
+                    let g = parse_number(next_token(bytes, &mut cursor)?)? as f32;
+                    let b = parse_number(next_token(bytes, &mut cursor)?)? as f32;
+                    pixels.push(Color::new(r, g, b) / max_value);
+                }
+            },
+            b"P6" => {
+                // exactly one whitespace byte separates the header from the binary data
+                let data = bytes.get(cursor + 1..).unwrap_or(&[]);
+                if data.len() < pixel_count * 3 {
+                    return Err(invalid_data("PPM image data is truncated"));
+                }
+
+                for rgb in data.chunks_exact(3).take(pixel_count) {
+                    pixels.push(Color::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / max_value);
+                }
+            },
+            _ => return Err(invalid_data("not a PPM image (expected P3 or P6)")),
+        }
+
+        return Ok(ImageTexture::new(width, height, pixels));
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixel at column x and row y (row 0 is the top of the image)
+    pub fn pixel(&self, x: usize, y: usize) -> &Color {
+        &self.pixels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    /// Nearest-neighbor lookup, wrapping (u, v) so textures repeat outside of [0, 1]
+    fn value(&self, u: Float, v: Float, _point: &Point3) -> Color {
+        let u = u - u.floor();
+        let v = 1.0 - (v - v.floor());
+        let x = ((u * self.width as Float) as usize).min(self.width - 1);
+        let y = ((v * self.height as Float) as usize).min(self.height - 1);
+        return self.pixel(x, y).clone();
+    }
+}
+
+/// Returns the next whitespace-separated token, skipping "#" comments
+fn next_token<'a>(bytes: &'a [u8], cursor: &mut usize) -> io::Result<&'a [u8]> {
+    loop {
+        while *cursor < bytes.len() && bytes[*cursor].is_ascii_whitespace() {
+            *cursor += 1;
+        }
+
+        if *cursor < bytes.len() && bytes[*cursor] == b'#' {
+            while *cursor < bytes.len() && bytes[*cursor] != b'\n' {
+                *cursor += 1;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let start = *cursor;
+    while *cursor < bytes.len() && !bytes[*cursor].is_ascii_whitespace() {
+        *cursor += 1;
+    }
+
+    if start == *cursor {
+        return Err(invalid_data("PPM image ended unexpectedly"));
+    }
+
+    return Ok(&bytes[start..*cursor]);
+}
+
+fn parse_number(token: &[u8]) -> io::Result<usize> {
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|token| token.parse().ok())
+        .ok_or_else(|| invalid_data("PPM image contains an invalid number"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_parses_plain_ppm() {
+        let image = ImageTexture::parse_ppm(b"P3\n# a comment\n2 1\n255\n255 0 0  0 0 255\n").unwrap();
+
+        assert_eq!(2, image.width());
+        assert_eq!(1, image.height());
+        assert_eq!(Color::new(1.0, 0.0, 0.0), image.value(0.25, 0.5, &Point3::origin()));
+        assert_eq!(Color::new(0.0, 0.0, 1.0), image.value(0.75, 0.5, &Point3::origin()));
+    }
+
+    #[test]
+    fn image_rejects_truncated_binary_ppm() {
+        let result = ImageTexture::parse_ppm(b"P6\n2 2\n255\n\x00\x00\x00");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn image_rejects_sizes_the_data_cant_hold() {
+        let error = |bytes: &[u8]| ImageTexture::parse_ppm(bytes).err().map(|error| error.to_string());
+
+        assert_eq!(Some("PPM image is too large".to_string()), error(format!("P6\n{} {}\n255\n", usize::MAX, 2).as_bytes()));
+        assert_eq!(Some("PPM image data is truncated".to_string()), error(b"P3\n100000 100000\n255\n0 0 0\n"));
+        assert_eq!(Some("PPM image has no pixels".to_string()), error(b"P6\n0 4\n255\n"));
+        assert_eq!(Some("PPM image has no pixels".to_string()), error(b"P3\n4 0\n255\n"));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::Color;
+use crate::Float;
+use crate::Point3;
+
+pub use self::image::*;
+pub use self::solid::*;
+
+mod image;
+mod solid;
+
+/// Textures are looked up by the surface coordinates (u, v) of a hit, and the point in space that was hit
+/// The returned value is usually a color, but normal maps and bump maps reuse textures as "data" too
+/// Send + Sync because the materials holding them are, so a whole scene can be moved to other threads
+pub trait Texture: Send + Sync {
+    fn value(&self, u: Float, v: Float, point: &Point3) -> Color;
+}
@@ -0,0 +1,23 @@
+use crate::Color;
+use crate::Float;
+use crate::Point3;
+use crate::Texture;
+
+/// A Solid texture is the same value everywhere
+pub struct SolidTexture {
+    value: Color,
+}
+
+impl SolidTexture {
+    pub fn new(value: Color) -> Self {
+        SolidTexture {
+            value: value
+        }
+    }
+}
+
+impl Texture for SolidTexture {
+    fn value(&self, _u: Float, _v: Float, _point: &Point3) -> Color {
+        return self.value.clone();
+    }
+}
@@ -0,0 +1,61 @@
+use std::ops;
+
+/// Linear RGB, usually radiance or a reflectance (albedo) between 0 and 1
+/// Colors multiply component-wise: light of one color reflecting off a surface of another
+#[derive(Debug, PartialEq, Clone)]
+pub struct Color {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl Color {
+    pub fn new(r: f32, g: f32, b: f32) -> Self {
+        Color { r: r, g: g, b: b }
+    }
+
+    pub fn black() -> Self {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn white() -> Self {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// The same value in all three channels
+    pub fn gray(value: f32) -> Self {
+        Color::new(value, value, value)
+    }
+
+    pub fn r(&self) -> f32 {
+        self.r
+    }
+
+    pub fn g(&self) -> f32 {
+        self.g
+    }
+
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    pub fn max_component(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Applies function to every channel
+    pub fn map(&self, function: impl Fn(f32) -> f32) -> Color {
+        Color::new(function(self.r), function(self.g), function(self.b))
+    }
+}
+
+binary_op!(ops::Add, add, Color, Color => Color, |color, other| Color::new(color.r + other.r, color.g + other.g, color.b + other.b));
+binary_op!(ops::Sub, sub, Color, Color => Color, |color, other| Color::new(color.r - other.r, color.g - other.g, color.b - other.b));
+binary_op!(ops::Mul, mul, Color, Color => Color, |color, other| Color::new(color.r * other.r, color.g * other.g, color.b * other.b));
+binary_op!(ops::Mul, mul, Color, f32 => Color, |color, scale| Color::new(color.r * scale, color.g * scale, color.b * scale));
+binary_op!(ops::Mul, mul, f32, Color => Color, |scale, color| Color::new(scale * color.r, scale * color.g, scale * color.b));
+binary_op!(ops::Div, div, Color, f32 => Color, |color, scale| Color::new(color.r / scale, color.g / scale, color.b / scale));
+assign_op!(ops::AddAssign, add_assign, Color, Color, |color, other| *color = &*color + other);
+assign_op!(ops::MulAssign, mul_assign, Color, Color, |color, other| *color = &*color * other);
+assign_op!(ops::MulAssign, mul_assign, Color, f32, |color, scale| *color = &*color * *scale);
+assign_op!(ops::DivAssign, div_assign, Color, f32, |color, scale| *color = &*color / *scale);
@@ -0,0 +1,79 @@
+use crate::Float;
+use crate::Normal3;
+use crate::Vector3;
+
+/// An orthonormal basis around a normal, for working in local shading space
+/// In local space the normal is +Z and the tangents are +X and +Y, so the cosine to the normal is just the Z of a direction
+/// and the warps of the sampling module can be used as they are
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    tangent: Vector3,
+    bitangent: Vector3,
+    normal: Vector3,
+}
+
+impl Frame {
+    /// Builds the tangents without branching on which axis the normal is closest to, and without a discontinuity
+    /// except right at normal.z == -0.0, from Duff et al. "Building an Orthonormal Basis, Revisited" (2017)
+    pub fn from_normal(normal: &Normal3) -> Self {
+        let (x, y, z) = (normal.x(), normal.y(), normal.z());
+        let sign = (1.0 as Float).copysign(z);
+        let a = -1.0 / (sign + z);
+        let b = x * y * a;
+        return Frame {
+            tangent: Vector3::new(1.0 + sign * x * x * a, sign * b, -sign * x),
+            bitangent: Vector3::new(b, sign + y * y * a, -y),
+            normal: normal.as_vector().clone(),
+        };
+    }
+
+    pub fn normal(&self) -> &Vector3 {
+        &self.normal
+    }
+
+    /// vector in the frame's coordinates, the normal is +Z
+    pub fn to_local(&self, vector: &Vector3) -> Vector3 {
+        return Vector3::new(
+            Vector3::dot(vector, &self.tangent),
+            Vector3::dot(vector, &self.bitangent),
+            Vector3::dot(vector, &self.normal));
+    }
+
+    /// A local vector back in world coordinates
+    pub fn to_world(&self, local: &Vector3) -> Vector3 {
+        return local.x() * &self.tangent + local.y() * &self.bitangent + local.z() * &self.normal;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_is_orthonormal_and_round_trips() {
+        let normals = [
+            Normal3::new(0.0, 0.0, 1.0),
+            Normal3::new(0.0, 0.0, -1.0),
+            Normal3::new(1.0, 0.0, 0.0),
+            Normal3::new(0.3, -0.8, 0.1),
+            Normal3::new(-0.2, 0.1, -0.97),
+        ];
+        let vector = Vector3::new(0.4, -1.5, 2.0);
+        for normal in normals.iter() {
+            let frame = Frame::from_normal(normal);
+            let axes = [&frame.tangent, &frame.bitangent, &frame.normal];
+            for (i, lhs) in axes.iter().enumerate() {
+                assert!((lhs.magnitude() - 1.0).abs() < 1e-5, "{:?}", normal);
+                for rhs in axes.iter().skip(i + 1) {
+                    assert!(Vector3::dot(lhs, rhs).abs() < 1e-5, "{:?}", normal);
+                }
+            }
+            // right handed, so tangent x bitangent is the normal
+            assert!(Vector3::dot(&frame.tangent.cross(&frame.bitangent), &frame.normal) > 0.99);
+
+            assert!((frame.to_local(normal.as_vector()).z() - 1.0).abs() < 1e-5);
+            let round_trip = frame.to_world(&frame.to_local(&vector));
+            assert!((&round_trip - &vector).magnitude() < 1e-5, "{:?}", normal);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! The geometric types: points, directions and normals are different things and transform differently,
+//! so they get different types with only the operations that make sense for them
+//!
+//! - Vector3 is a direction or offset: it can be scaled, added, crossed and dotted
+//! - Point3 is a position: points minus points give a Vector3, and points plus a Vector3 give a point
+//! - Normal3 is a unit surface normal, which transforms by the inverse transpose
+//! - Color is linear RGB, colors multiply component-wise
+//! - Frame is an orthonormal basis around a normal, for going to and from local shading space
+
+/// Implements a binary operator for every combination of owned and borrowed operands
+/// The body sees both operands as references: `binary_op!(ops::Add, add, Point3, Vector3 => Point3, |point, offset| ...)`
+macro_rules! binary_op {
+    ($($path:ident)::+, $fn:ident, $lhs:ty, $rhs:ty => $output:ty, |$a:ident, $b:ident| $body:expr) => {
+        impl $($path)::+<$rhs> for $lhs {
+            type Output = $output;
+
+            fn $fn(self, other: $rhs) -> $output {
+                let ($a, $b) = (&self, &other);
+                $body
+            }
+        }
+
+        impl $($path)::+<&$rhs> for $lhs {
+            type Output = $output;
+
+            fn $fn(self, other: &$rhs) -> $output {
+                let ($a, $b) = (&self, other);
+                $body
+            }
+        }
+
+        impl $($path)::+<$rhs> for &$lhs {
+            type Output = $output;
+
+            fn $fn(self, other: $rhs) -> $output {
+                let ($a, $b) = (self, &other);
+                $body
+            }
+        }
+
+        impl $($path)::+<&$rhs> for &$lhs {
+            type Output = $output;
+
+            fn $fn(self, other: &$rhs) -> $output {
+                let ($a, $b) = (self, other);
+                $body
+            }
+        }
+    };
+}
+
+/// Implements an assigning operator for an owned and a borrowed right hand side
+/// `assign_op!(ops::AddAssign, add_assign, Color, Color, |color, other| ...)`, where color is &mut
+macro_rules! assign_op {
+    ($($path:ident)::+, $fn:ident, $lhs:ty, $rhs:ty, |$a:ident, $b:ident| $body:expr) => {
+        impl $($path)::+<$rhs> for $lhs {
+            fn $fn(&mut self, other: $rhs) {
+                let ($a, $b) = (self, &other);
+                $body
+            }
+        }
+
+        impl $($path)::+<&$rhs> for $lhs {
+            fn $fn(&mut self, other: &$rhs) {
+                let ($a, $b) = (self, other);
+                $body
+            }
+        }
+    };
+}
+
+pub use self::color::Color;
+pub use self::frame::Frame;
+pub use self::normal::Normal3;
+pub use self::point::Point3;
+pub use self::transform::Transform;
+pub use self::vector::Vector3;
+
+mod color;
+mod frame;
+mod normal;
+mod point;
+mod transform;
+mod vector;
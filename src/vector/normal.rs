@@ -0,0 +1,63 @@
+use crate::Float;
+use crate::Vector3;
+
+use std::ops;
+
+/// A unit length surface normal
+/// Normals stay perpendicular to the surface under transforms only when transformed by the inverse transpose,
+/// see Transform::normal, so they are kept apart from plain vectors
+#[derive(Debug, PartialEq, Clone)]
+pub struct Normal3(Vector3);
+
+impl Normal3 {
+    /// Normalizes (x, y, z)
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Normal3::from_vector(&Vector3::new(x, y, z))
+    }
+
+    /// The normal pointing along vector, which doesn't have to be unit length
+    pub fn from_vector(vector: &Vector3) -> Self {
+        Normal3(Vector3::unit_vector(vector))
+    }
+
+    pub fn as_vector(&self) -> &Vector3 {
+        &self.0
+    }
+
+    pub fn x(&self) -> Float {
+        self.0.x()
+    }
+
+    pub fn y(&self) -> Float {
+        self.0.y()
+    }
+
+    pub fn z(&self) -> Float {
+        self.0.z()
+    }
+
+    pub fn dot(&self, vector: &Vector3) -> Float {
+        Vector3::dot(&self.0, vector)
+    }
+
+    /// This normal, flipped if needed to be on the same side as direction
+    pub fn facing(&self, direction: &Vector3) -> Normal3 {
+        if self.dot(direction) < 0.0 { -self } else { self.clone() }
+    }
+}
+
+impl ops::Neg for Normal3 {
+    type Output = Normal3;
+
+    fn neg(self) -> Normal3 {
+        Normal3(-self.0)
+    }
+}
+
+impl ops::Neg for &Normal3 {
+    type Output = Normal3;
+
+    fn neg(self) -> Normal3 {
+        Normal3(-&self.0)
+    }
+}
@@ -0,0 +1,51 @@
+use crate::Float;
+use crate::Vector3;
+
+use std::ops;
+
+/// A position in space
+/// Subtracting points gives the Vector3 between them, adding a Vector3 to a point moves it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Point3(Vector3);
+
+impl Point3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Point3(Vector3::new(x, y, z))
+    }
+
+    pub fn origin() -> Self {
+        Point3::new(0.0, 0.0, 0.0)
+    }
+
+    /// The point at offset from the origin
+    pub fn from_vector(offset: Vector3) -> Self {
+        Point3(offset)
+    }
+
+    /// The offset of this point from the origin
+    pub fn as_vector(&self) -> &Vector3 {
+        &self.0
+    }
+
+    pub fn x(&self) -> Float {
+        self.0.x()
+    }
+
+    pub fn y(&self) -> Float {
+        self.0.y()
+    }
+
+    pub fn z(&self) -> Float {
+        self.0.z()
+    }
+
+    pub fn distance(&self, other: &Point3) -> Float {
+        (self - other).magnitude()
+    }
+}
+
+binary_op!(ops::Sub, sub, Point3, Point3 => Vector3, |point, other| &point.0 - &other.0);
+binary_op!(ops::Add, add, Point3, Vector3 => Point3, |point, offset| Point3(&point.0 + offset));
+binary_op!(ops::Sub, sub, Point3, Vector3 => Point3, |point, offset| Point3(&point.0 - offset));
+assign_op!(ops::AddAssign, add_assign, Point3, Vector3, |point, offset| point.0 += offset);
+assign_op!(ops::SubAssign, sub_assign, Point3, Vector3, |point, offset| point.0 -= offset);
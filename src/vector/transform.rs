@@ -0,0 +1,193 @@
+use crate::Float;
+use crate::Normal3;
+use crate::Point3;
+use crate::Vector3;
+
+/// An affine transform, kept together with its inverse
+/// Points get the translation, vectors don't, and normals go through the inverse transpose so they stay
+/// perpendicular to surfaces that were scaled unevenly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    /// Row-major, the last row is always (0, 0, 0, 1)
+    matrix: [[Float; 4]; 4],
+    inverse: [[Float; 4]; 4],
+}
+
+const IDENTITY: [[Float; 4]; 4] = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    pub fn translation(offset: &Vector3) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for (axis, value) in [offset.x(), offset.y(), offset.z()].iter().enumerate() {
+            matrix[axis][3] = *value;
+            inverse[axis][3] = -*value;
+        }
+        Transform { matrix: matrix, inverse: inverse }
+    }
+
+    /// Scales along each axis, none of the factors may be 0
+    pub fn scaling(x: Float, y: Float, z: Float) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for (axis, value) in [x, y, z].iter().enumerate() {
+            matrix[axis][axis] = *value;
+            inverse[axis][axis] = 1.0 / *value;
+        }
+        Transform { matrix: matrix, inverse: inverse }
+    }
+
+    /// Rotates angle_deg counterclockwise around axis (looking down the axis towards the origin)
+    pub fn rotation(axis: &Vector3, angle_deg: Float) -> Self {
+        let axis = Vector3::unit_vector(axis);
+        let (x, y, z) = (axis.x(), axis.y(), axis.z());
+        let (sin, cos) = angle_deg.to_radians().sin_cos();
+        let matrix = [
+            [cos + x * x * (1.0 - cos), x * y * (1.0 - cos) - z * sin, x * z * (1.0 - cos) + y * sin, 0.0],
+            [y * x * (1.0 - cos) + z * sin, cos + y * y * (1.0 - cos), y * z * (1.0 - cos) - x * sin, 0.0],
+            [z * x * (1.0 - cos) - y * sin, z * y * (1.0 - cos) + x * sin, cos + z * z * (1.0 - cos), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // rotations are orthogonal, the inverse is the transpose
+        Transform { matrix: matrix, inverse: transpose(&matrix) }
+    }
+
+    /// A transform from a row-major affine matrix, None if it can't be inverted
+    pub fn from_matrix(matrix: [[Float; 4]; 4]) -> Option<Self> {
+        let inverse = invert(&matrix)?;
+        Some(Transform { matrix: matrix, inverse: inverse })
+    }
+
+    pub fn matrix(&self) -> &[[Float; 4]; 4] {
+        &self.matrix
+    }
+
+    /// This transform followed by next
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: multiply(&next.matrix, &self.matrix),
+            inverse: multiply(&self.inverse, &next.inverse),
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, point: &Point3) -> Point3 {
+        let m = &self.matrix;
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        Point3::new(
+            m[0][0] * x + m[0][1] * y + m[0][2] * z + m[0][3],
+            m[1][0] * x + m[1][1] * y + m[1][2] * z + m[1][3],
+            m[2][0] * x + m[2][1] * y + m[2][2] * z + m[2][3],
+        )
+    }
+
+    pub fn vector(&self, vector: &Vector3) -> Vector3 {
+        let m = &self.matrix;
+        let (x, y, z) = (vector.x(), vector.y(), vector.z());
+        Vector3::new(
+            m[0][0] * x + m[0][1] * y + m[0][2] * z,
+            m[1][0] * x + m[1][1] * y + m[1][2] * z,
+            m[2][0] * x + m[2][1] * y + m[2][2] * z,
+        )
+    }
+
+    /// Transforms by the inverse transpose, and renormalizes
+    pub fn normal(&self, normal: &Normal3) -> Normal3 {
+        let m = &self.inverse;
+        let (x, y, z) = (normal.x(), normal.y(), normal.z());
+        Normal3::new(
+            m[0][0] * x + m[1][0] * y + m[2][0] * z,
+            m[0][1] * x + m[1][1] * y + m[2][1] * z,
+            m[0][2] * x + m[1][2] * y + m[2][2] * z,
+        )
+    }
+}
+
+fn multiply(lhs: &[[Float; 4]; 4], rhs: &[[Float; 4]; 4]) -> [[Float; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (column, value) in result_row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| lhs[row][k] * rhs[k][column]).sum();
+        }
+    }
+    return result;
+}
+
+fn transpose(matrix: &[[Float; 4]; 4]) -> [[Float; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (column, value) in result_row.iter_mut().enumerate() {
+            *value = matrix[column][row];
+        }
+    }
+    return result;
+}
+
+/// Gauss-Jordan elimination with partial pivoting
+fn invert(matrix: &[[Float; 4]; 4]) -> Option<[[Float; 4]; 4]> {
+    let mut left = *matrix;
+    let mut right = IDENTITY;
+    for column in 0..4 {
+        let pivot = (column..4).max_by(|a, b| left[*a][column].abs().total_cmp(&left[*b][column].abs()))?;
+        if left[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+
+        left.swap(column, pivot);
+        right.swap(column, pivot);
+        let scale = 1.0 / left[column][column];
+        for k in 0..4 {
+            left[column][k] *= scale;
+            right[column][k] *= scale;
+        }
+
+        for row in 0..4 {
+            if row != column {
+                let factor = left[row][column];
+                for k in 0..4 {
+                    left[row][k] -= factor * left[column][k];
+                    right[row][k] -= factor * right[column][k];
+                }
+            }
+        }
+    }
+
+    return Some(right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_keeps_normals_perpendicular() {
+        // a plane tilted 45 degrees, squashed along x
+        let transform = Transform::scaling(0.25, 1.0, 1.0).then(&Transform::translation(&Vector3::new(1.0, 2.0, 3.0)));
+        let normal = Normal3::new(1.0, 1.0, 0.0);
+        let along_surface = Vector3::new(1.0, -1.0, 0.0);
+
+        let transformed_normal = transform.normal(&normal);
+        assert!(transformed_normal.dot(&transform.vector(&along_surface)).abs() < 1e-6);
+        // transforming the normal like a vector would tilt it off the surface
+        assert!(Vector3::dot(&transform.vector(normal.as_vector()), &transform.vector(&along_surface)).abs() > 0.1);
+
+        assert_eq!(Point3::new(1.25, 3.0, 3.0), transform.point(&Point3::new(1.0, 1.0, 0.0)));
+        let round_trip = transform.inverse().point(&transform.point(&Point3::new(4.0, 5.0, 6.0)));
+        assert!(round_trip.distance(&Point3::new(4.0, 5.0, 6.0)) < 1e-5);
+        let inverted = Transform::from_matrix(*transform.matrix()).unwrap();
+        assert!(inverted.inverse().point(&Point3::new(1.25, 3.0, 3.0)).distance(&Point3::new(1.0, 1.0, 0.0)) < 1e-5);
+    }
+}
@@ -1,12 +1,13 @@
-use std::ops;
+use crate::Float;
+use crate::Normal3;
 
-use rand::prelude::*;
+use std::ops;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Vector3 {
-    e0: f32,
-    e1: f32,
-    e2: f32,
+    e0: Float,
+    e1: Float,
+    e2: Float,
 }
 
 impl Vector3 {
@@ -15,88 +16,46 @@ impl Vector3 {
         return vector / mag;
     }
 
-    pub fn dot(lhs: &Vector3, rhs: &Vector3) -> f32 {
+    pub fn dot(lhs: &Vector3, rhs: &Vector3) -> Float {
         return lhs.e0 * rhs.e0 + lhs.e1 * rhs.e1 + lhs.e2 * rhs.e2;
     }
 
-    /// Returns a random Disk with X,Y values (Z is clamped to 0)
-    pub fn random_in_disk() -> Vector3 {
-        let mut rng = rand::thread_rng();
-        loop {
-            let disk = 2.0 * Vector3::new(rng.gen::<f32>(), rng.gen::<f32>(), 0.0) - Vector3::new(1.0, 1.0, 0.0);
-            if Vector3::dot(&disk, &disk) < 1.0 {
-                return disk;
-            }
-        }
-    }
-
-    /// Returns a random Sphere with X,Y,Z values
-    pub fn random_in_unit_sphere() -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let mut point: Vector3;
-        loop {
-            point = Vector3::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()) * 2.0 -1.0;
-
-            if point.magnitude_squared() < 1.0 {
-                break;
-            }
-        }
-
-        return point;
-    }
-
     /// Reflects the vector_to_reflect along the normal_of_reflection
-    pub fn reflect(vector_to_reflect: &Vector3, normal_of_reflection: &Vector3) -> Vector3 {
+    pub fn reflect(vector_to_reflect: &Vector3, normal_of_reflection: &Normal3) -> Vector3 {
+        let normal_of_reflection = normal_of_reflection.as_vector();
         // dot product produces the "amount of vector in the other vector"
         // so this is perfect for reflection
         // 2 * the dot because the vector_to_reflect will go in that direction beyond the normal, so it needs "two times the distance in the normal traveled" to determine its "real" spot
         // https://math.stackexchange.com/questions/13261/how-to-get-a-reflection-vector 
-        return vector_to_reflect - 2f32 * Vector3::dot(vector_to_reflect, normal_of_reflection) * normal_of_reflection;
+        return vector_to_reflect - 2.0 * Vector3::dot(vector_to_reflect, normal_of_reflection) * normal_of_reflection;
     }
 
-    /// Attempts to refract the vector_to_refract along the normal_of_refraction with the refraction index
-    /// 
-    /// Will return "None" if there are no real solutions (all light is absorbed in the refraction process)
-    /// Otherwise returns the refracted vector
-    /// 
-    /// This is an implementation of Schnell's Law in Vector Form
+    /// Refracts incident through a smooth boundary by Snell's law, or None past the critical angle (total internal reflection)
     /// https://en.wikipedia.org/wiki/Snell%27s_law#Vector_form
-    /// 
-    /// vector_to_refract is the "Light Vector" (pointing from the light source toward the surface of the geometry)
-    /// normal_of_refraction is the normalized "plane vector"
-    /// refraction_index_differential is the form of "N / N'" - the refraction index of the original medium divided by the refraction index of the geometric medium
-    /// 
+    ///
+    /// incident points toward the surface (the way the ray travels), and doesn't have to be unit length
+    /// normal can be on either side of the surface, it is flipped to face against incident
+    /// eta is the refractive index on the far side over the index on the incident side, so 1.5 going from air into glass
+    /// The refracted direction is unit length, and pointing away from the surface on the far side
+    ///
     /// For more info on refraction index, this link helps: http://hyperphysics.phy-astr.gsu.edu/hbase/geoopt/refr.html
-    pub fn refract(vector_to_refract: &Vector3, normal_of_refraction: &Vector3, refraction_index_differential: f32) -> Option<Vector3> {
-        // to understand this read up on the wikipedia article
-        let vector_to_refract = Vector3::unit_vector(&vector_to_refract);
-        let c = Vector3::dot(&vector_to_refract, &-normal_of_refraction);
-        let discriminant = 1f32 - refraction_index_differential * refraction_index_differential * (1f32 - c * c);
-        if discriminant <= 0.0 {
-            // All light was absorbed as part of the refraction (no real solution)
-            return None;
-        }
-
-        let refracted = (refraction_index_differential * vector_to_refract) + (normal_of_refraction * (refraction_index_differential * c - discriminant.sqrt()));
-        return Some(refracted);
-    }
-
-    pub fn refract_book(vector_to_refract: &Vector3, normal_of_refraction: &Vector3, refraction_index_differential: f32) -> Option<Vector3> {
-        // to understand this read up on the wikipedia article
-        let vector_to_refract = Vector3::unit_vector(&vector_to_refract);
-        let dt = Vector3::dot(&vector_to_refract, normal_of_refraction);
-        let discriminant = 1.0 - refraction_index_differential * refraction_index_differential * (1.0 - dt * dt);
-        if discriminant <= 0.0 {
+    pub fn refract(incident: &Vector3, normal: &Normal3, eta: Float) -> Option<Vector3> {
+        let incident = Vector3::unit_vector(incident);
+        let normal = normal.facing(&-&incident);
+        let normal = normal.as_vector();
+        let cos_incident = -Vector3::dot(&incident, normal);
+        let sin2_transmitted = (1.0 - cos_incident * cos_incident).max(0.0) / (eta * eta);
+        if sin2_transmitted >= 1.0 {
             return None;
         }
 
-        let refraction = refraction_index_differential * (vector_to_refract - normal_of_refraction * dt) - normal_of_refraction * discriminant.sqrt();
-        return Some(refraction)
+        let cos_transmitted = (1.0 - sin2_transmitted).sqrt();
+        return Some(incident / eta + (cos_incident / eta - cos_transmitted) * normal);
     }
 }
 
 impl Vector3 {
-    pub fn new(x: f32, y: f32, z: f32) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Vector3 { e0: x, e1: y, e2: z}
     }
 
@@ -108,35 +67,23 @@ impl Vector3 {
         }
     }
 
-    pub fn magnitude(&self) -> f32 {
+    pub fn magnitude(&self) -> Float {
         return (self.e0.powi(2) + self.e1.powi(2) + self.e2.powi(2)).sqrt();
     }
 
-    pub fn magnitude_squared(&self) -> f32 {
+    pub fn magnitude_squared(&self) -> Float {
         return self.e0.powi(2) + self.e1.powi(2) + self.e2.powi(2);
     }
     
-    pub fn x(&self) -> f32 {
-        self.e0
-    }
-    
-    pub fn y(&self) -> f32 {
-        self.e1
-    }
-    
-    pub fn z(&self) -> f32 {
-        self.e2
-    }
-    
-    pub fn r(&self) -> f32 {
+    pub fn x(&self) -> Float {
         self.e0
     }
     
-    pub fn g(&self) -> f32 {
+    pub fn y(&self) -> Float {
         self.e1
     }
     
-    pub fn b(&self) -> f32 {
+    pub fn z(&self) -> Float {
         self.e2
     }
 }
@@ -219,7 +166,7 @@ macro_rules! Vector3_Vector3_opassign {
     };
 }
 
-/// Generates the operations for method assignment. `my_vec += f32`
+/// Generates the operations for method assignment. `my_vec += Float`
 /// `Vector3_opassign(ops:AddAssign, add_assign)` (note the camelcase add_assign name)
 macro_rules! Vector3_opassign {
     ($($path:ident)::+, $fn:ident, $ty:ty) => {
@@ -233,16 +180,16 @@ macro_rules! Vector3_opassign {
     }
 }
 
-/// Generates the operations for the method. `let result = my_vec + 4f32`
+/// Generates the operations for the method. `let result = my_vec + 4.0`
 /// Handles `Vector3, T`, `T, Vector3`, `&Vector3, T`, `T, &Vector3`
-/// `Vector3_op!(ops:Add, add, f32)`
+/// `Vector3_op!(ops:Add, add, Float)`
 macro_rules! Vector3_op {
     ($($path:ident)::+, $fn:ident, $ty:ty) => {
         // impl ops::Add::add for Vector3
         impl $($path)::+<$ty> for Vector3 {
             type Output = Vector3;
 
-            // fn add(self, other: f32) -> Self::Output
+            // fn add(self, other: Float) -> Self::Output
             fn $fn(self, other: $ty) -> Self::Output {
                 Vector3 {
                     // e0: self.e0.add(other)
@@ -304,15 +251,11 @@ macro_rules! Vector3_op_for {
     };
 }
 
-Vector3_op_for!(f32);
+Vector3_op_for!(Float);
 Vector3_Vector3_op!(ops::Add, add);
 Vector3_Vector3_op!(ops::Sub, sub);
-Vector3_Vector3_op!(ops::Mul, mul);
-Vector3_Vector3_op!(ops::Div, div);
 Vector3_Vector3_opassign!(ops::AddAssign, add_assign);
 Vector3_Vector3_opassign!(ops::SubAssign, sub_assign);
-Vector3_Vector3_opassign!(ops::MulAssign, mul_assign);
-Vector3_Vector3_opassign!(ops::DivAssign, div_assign);
 
 impl ops::Neg for Vector3 {
     type Output = Vector3;